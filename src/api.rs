@@ -0,0 +1,137 @@
+/*!
+The BrasilApi operations as a trait, so services can depend on `dyn BrasilApi` (or a generic) instead of the client <br />
+`BrasilApiClient` implements it, and with the `testing` feature `fake::FakeBrasilApi` answers from memory
+*/
+use crate::{
+    banks::{BankResponseData, Ispb},
+    cep::{BestEffortCep, CepResponseData, EnumCepRequestVersion},
+    client::BrasilApiClient,
+    contact::ContactCheck,
+    ddd::{phone::PhoneNumber, DDDResponseData},
+    errors::Error,
+    holidays::HolidaysResponseData,
+    pix::{PixInstitution, PixParticipant},
+};
+use async_trait::async_trait;
+
+/// In-memory `BrasilApi`, for unit tests
+#[cfg(feature = "testing")]
+pub mod fake;
+
+/**
+One method per BrasilApi endpoint, same signatures as the `BrasilApiClient` methods <br />
+The higher-level operations have defaults built on them, so an implementation only needs the endpoints
+*/
+#[async_trait]
+pub trait BrasilApi: Send + Sync {
+    /// cep/v1 or cep/v2
+    async fn get_cep(&self, cep: &str, cep_version: Option<EnumCepRequestVersion>) -> Result<CepResponseData, Error>;
+
+    /// banks/v1
+    async fn get_banks(&self) -> Result<Vec<BankResponseData>, Error>;
+
+    /// banks/v1/{code}
+    async fn get_banks_by_code(&self, code: &i16) -> Result<BankResponseData, Error>;
+
+    /// ddd/v1/{ddd}
+    async fn get_city_and_states_by_ddd(&self, ddd: &i8) -> Result<DDDResponseData, Error>;
+
+    /// feriados/v1/{year}
+    async fn get_holidays(&self, year: &i32) -> Result<Vec<HolidaysResponseData>, Error>;
+
+    /// pix/v1/participants
+    async fn get_pix_participants(&self) -> Result<Vec<PixParticipant>, Error>;
+
+    /// See `BrasilApiClient::get_cep_best_effort`
+    async fn get_cep_best_effort(&self, cep: &str) -> Result<BestEffortCep, Error> {
+        crate::cep::cep_best_effort(self, cep).await
+    }
+
+    /// See `BrasilApiClient::validate_contact`
+    async fn validate_contact(&self, cep: &str, phone: &PhoneNumber) -> Result<ContactCheck, Error> {
+        crate::contact::validate_contact(self, cep, phone).await
+    }
+
+    /// See `BrasilApiClient::get_holidays_range`
+    async fn get_holidays_range(&self, from_year: &i32, to_year: &i32) -> Result<Vec<HolidaysResponseData>, Error> {
+        crate::holidays::holidays_range(self, from_year, to_year).await
+    }
+
+    /// Searches the whole `get_banks` list, the client answers from its cached `bank_directory` instead
+    async fn get_bank_by_ispb(&self, ispb: &Ispb) -> Result<BankResponseData, Error> {
+        let banks = self.get_banks().await?;
+        banks.into_iter().find(|bank| bank.ispb.as_ref() == Some(ispb)).ok_or_else(|| crate::banks::ispb_not_found(ispb))
+    }
+
+    /// Requests the participants and the banks, the client uses its cached `bank_directory` for the banks
+    async fn get_pix_institution(&self, ispb: &Ispb) -> Result<PixInstitution, Error> {
+        let (participants, banks) = futures::future::join(self.get_pix_participants(), self.get_banks()).await;
+        Ok(PixInstitution {
+            participant: crate::pix::find_participant(participants?.iter(), ispb)?,
+            bank: banks?.into_iter().find(|bank| bank.ispb.as_ref() == Some(ispb)),
+        })
+    }
+}
+
+#[async_trait]
+impl BrasilApi for BrasilApiClient {
+    async fn get_cep(&self, cep: &str, cep_version: Option<EnumCepRequestVersion>) -> Result<CepResponseData, Error> {
+        BrasilApiClient::get_cep(self, cep, cep_version).await
+    }
+
+    async fn get_banks(&self) -> Result<Vec<BankResponseData>, Error> {
+        BrasilApiClient::get_banks(self).await
+    }
+
+    async fn get_banks_by_code(&self, code: &i16) -> Result<BankResponseData, Error> {
+        BrasilApiClient::get_banks_by_code(self, code).await
+    }
+
+    async fn get_city_and_states_by_ddd(&self, ddd: &i8) -> Result<DDDResponseData, Error> {
+        BrasilApiClient::get_city_and_states_by_ddd(self, ddd).await
+    }
+
+    async fn get_holidays(&self, year: &i32) -> Result<Vec<HolidaysResponseData>, Error> {
+        BrasilApiClient::get_holidays(self, year).await
+    }
+
+    async fn get_pix_participants(&self) -> Result<Vec<PixParticipant>, Error> {
+        BrasilApiClient::get_pix_participants(self).await
+    }
+
+    async fn get_bank_by_ispb(&self, ispb: &Ispb) -> Result<BankResponseData, Error> {
+        BrasilApiClient::get_bank_by_ispb(self, ispb).await
+    }
+
+    async fn get_pix_institution(&self, ispb: &Ispb) -> Result<PixInstitution, Error> {
+        BrasilApiClient::get_pix_institution(self, ispb).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::tests::{mock_server, MockResponse}, ddd::index::tests::ddd_response};
+    use futures_await_test::async_test;
+
+    /// What a service depending on the trait looks like
+    async fn state_of(api: &dyn BrasilApi, ddd: i8) -> Result<String, Error> {
+        Ok(api.get_city_and_states_by_ddd(&ddd).await?.state)
+    }
+
+    #[async_test]
+    async fn test_client_as_trait_object() {
+        let server = mock_server(|path| match path {
+            "/banks/v1" => MockResponse::json(200, r#"[{"ispb":"00000000","name":"BCO DO BRASIL S.A.","code":1,"fullName":"Banco do Brasil S.A."}]"#),
+            path => ddd_response(path),
+        });
+        let api: Box<dyn BrasilApi> = Box::new(server.client());
+
+        assert_eq!(state_of(api.as_ref(), 21).await.unwrap(), "RJ");
+        assert!(state_of(api.as_ref(), 31).await.is_err());
+        assert_eq!(api.get_banks().await.unwrap()[0].code, Some(1));
+        // Validated before any request, as with the client
+        assert!(matches!(api.get_holidays(&1800).await, Err(Error::InvalidInputRangeError { .. })));
+        assert_eq!(server.hits(), 3);
+    }
+}
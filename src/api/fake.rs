@@ -0,0 +1,234 @@
+/*!
+`FakeBrasilApi`: a `BrasilApi` answering from data given upfront, with no requests at all <br />
+Inputs are validated like the client does, unknown values fail with `Error::NotFoundError`,
+and a whole endpoint family can be made to fail (i.e: to test a BrasilApi outage)
+*/
+use super::BrasilApi;
+use crate::{
+    banks::BankResponseData,
+    cep::{Cep, CepResponseData, EnumCepRequestVersion, Location},
+    client::EndpointFamily,
+    constants::{banks::{MAX_CODE, MIN_CODE}, ddd::{MAX_DDD, MIN_DDD}, holidays::{MAX_YEAR, MIN_YEAR}},
+    ddd::DDDResponseData,
+    errors::Error,
+    holidays::HolidaysResponseData,
+    pix::PixParticipant,
+};
+use async_trait::async_trait;
+use chrono::Datelike;
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+/// In-memory `BrasilApi`, clones share the recorded calls
+#[derive(Debug, Clone, Default)]
+pub struct FakeBrasilApi {
+    ceps: HashMap<String, CepResponseData>,
+    banks: Vec<BankResponseData>,
+    ddds: HashMap<i8, DDDResponseData>,
+    holidays: HashMap<i32, Vec<HolidaysResponseData>>,
+    pix_participants: Vec<PixParticipant>,
+    failures: HashMap<EndpointFamily, Error>,
+    calls: Arc<Mutex<Vec<EndpointFamily>>>,
+}
+
+impl FakeBrasilApi {
+    pub fn new() -> FakeBrasilApi {
+        FakeBrasilApi::default()
+    }
+
+    /// Answered for its `cep` (V1 answers leave the location out), panics when that isn't a valid CEP
+    pub fn with_cep(mut self, data: CepResponseData) -> FakeBrasilApi {
+        let cep: Cep = data.cep.parse().expect("the fake CEP data should have a valid CEP");
+        self.ceps.insert(cep.into(), data);
+        self
+    }
+
+    /// Listed by `get_banks`, and found by `get_banks_by_code` when it has a code
+    pub fn with_bank(mut self, bank: BankResponseData) -> FakeBrasilApi {
+        self.banks.push(bank);
+        self
+    }
+
+    pub fn with_ddd(mut self, ddd: i8, data: DDDResponseData) -> FakeBrasilApi {
+        self.ddds.insert(ddd, data);
+        self
+    }
+
+    /// Grouped by year, a year without any holiday fails with `Error::NotFoundError`
+    pub fn with_holidays(mut self, holidays: impl IntoIterator<Item = HolidaysResponseData>) -> FakeBrasilApi {
+        for holiday in holidays {
+            self.holidays.entry(holiday.date.year()).or_default().push(holiday);
+        }
+        self
+    }
+
+    pub fn with_pix_participant(mut self, participant: PixParticipant) -> FakeBrasilApi {
+        self.pix_participants.push(participant);
+        self
+    }
+
+    /// Every valid call of the family fails with `error` (invalid inputs still fail first)
+    pub fn with_failure(mut self, family: EndpointFamily, error: Error) -> FakeBrasilApi {
+        self.failures.insert(family, error);
+        self
+    }
+
+    /// How many calls of the family got past the input validation
+    pub fn calls(&self, family: EndpointFamily) -> usize {
+        self.calls.lock().unwrap().iter().filter(|call| **call == family).count()
+    }
+
+    /// Records the call, failing when the family was set to
+    fn call(&self, family: EndpointFamily) -> Result<(), Error> {
+        self.calls.lock().unwrap().push(family);
+        match self.failures.get(&family) {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn not_found(name: &str, value: impl ToString) -> Error {
+    Error::NotFoundError { name: name.to_string(), value: value.to_string() }
+}
+
+fn check_range(name: &str, value: i32, min: i32, max: i32) -> Result<(), Error> {
+    if value < min || value > max {
+        return Err(Error::InvalidInputRangeError { name: name.to_string(), min, max });
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl BrasilApi for FakeBrasilApi {
+    async fn get_cep(&self, cep: &str, cep_version: Option<EnumCepRequestVersion>) -> Result<CepResponseData, Error> {
+        let cep: Cep = cep.parse()?;
+        self.call(EndpointFamily::Cep)?;
+
+        let mut data = self.ceps.get(cep.as_str()).cloned().ok_or_else(|| not_found("cep", &cep))?;
+        if cep_version != Some(EnumCepRequestVersion::V2) {
+            data.location = Location::default();
+        }
+        Ok(data)
+    }
+
+    async fn get_banks(&self) -> Result<Vec<BankResponseData>, Error> {
+        self.call(EndpointFamily::Banks)?;
+        Ok(self.banks.clone())
+    }
+
+    async fn get_banks_by_code(&self, code: &i16) -> Result<BankResponseData, Error> {
+        check_range("code", *code as i32, *MIN_CODE as i32, *MAX_CODE as i32)?;
+        self.call(EndpointFamily::Banks)?;
+        self.banks.iter().find(|bank| bank.code == Some(*code)).cloned().ok_or_else(|| not_found("code", code))
+    }
+
+    async fn get_city_and_states_by_ddd(&self, ddd: &i8) -> Result<DDDResponseData, Error> {
+        check_range("ddd", *ddd as i32, *MIN_DDD as i32, *MAX_DDD as i32)?;
+        self.call(EndpointFamily::Ddd)?;
+        self.ddds.get(ddd).cloned().ok_or_else(|| not_found("ddd", ddd))
+    }
+
+    async fn get_holidays(&self, year: &i32) -> Result<Vec<HolidaysResponseData>, Error> {
+        check_range("year", *year, *MIN_YEAR, *MAX_YEAR)?;
+        self.call(EndpointFamily::Holidays)?;
+        let mut holidays = self.holidays.get(year).cloned().ok_or_else(|| not_found("year", year))?;
+        holidays.sort_by_key(|holiday| holiday.date);
+        Ok(holidays)
+    }
+
+    async fn get_pix_participants(&self) -> Result<Vec<PixParticipant>, Error> {
+        self.call(EndpointFamily::Pix)?;
+        Ok(self.pix_participants.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{banks::{directory::BankDirectory, Ispb}, calendar::{OnlineBusinessCalendar, WeekendRule}, contact::ContactVerdict, ddd::phone::PhoneNumber, holidays::HolidayType};
+    use futures_await_test::async_test;
+
+    fn fake() -> FakeBrasilApi {
+        FakeBrasilApi::new()
+            .with_cep(serde_json::from_str(
+                r#"{"cep":"01402000","state":"SP","city":"São Paulo","neighborhood":"Jardim Paulista","street":"Avenida Brigadeiro Luís Antônio","service":"fake",
+                "location":{"type":"Point","coordinates":{"longitude":"-46.6573802","latitude":"-23.57555"}}}"#
+            ).unwrap())
+            .with_bank(serde_json::from_str(r#"{"ispb":"00000000","name":"BCO DO BRASIL S.A.","code":1,"fullName":"Banco do Brasil S.A."}"#).unwrap())
+            .with_ddd(11, DDDResponseData { state: "SP".to_string(), cities: vec!["SÃO PAULO".to_string()] })
+            .with_holidays(vec![
+                HolidaysResponseData { date: chrono::NaiveDate::from_ymd(2026, 12, 25), name: "Natal".to_string(), r#type: HolidayType::National },
+                HolidaysResponseData { date: chrono::NaiveDate::from_ymd(2026, 1, 1), name: "Confraternização mundial".to_string(), r#type: HolidayType::National },
+            ])
+    }
+
+    #[async_test]
+    async fn test_answers_from_memory() {
+        let api: &dyn BrasilApi = &fake();
+
+        let v2 = api.get_cep("01402-000", Some(EnumCepRequestVersion::V2)).await.unwrap();
+        assert!(v2.coordinates().is_some());
+        assert!(api.get_cep("01402000", None).await.unwrap().coordinates().is_none());
+        assert!(matches!(api.get_cep("01001-000", None).await, Err(Error::NotFoundError { .. })));
+        assert!(matches!(api.get_cep("0100", None).await, Err(Error::InvalidInputLenError { .. })));
+
+        assert_eq!(api.get_banks().await.unwrap().len(), 1);
+        assert_eq!(api.get_banks_by_code(&1).await.unwrap().name.as_deref(), Some("BCO DO BRASIL S.A."));
+        assert!(matches!(api.get_banks_by_code(&33).await, Err(Error::NotFoundError { .. })));
+        assert!(matches!(api.get_banks_by_code(&0).await, Err(Error::InvalidInputRangeError { .. })));
+
+        assert_eq!(api.get_city_and_states_by_ddd(&11).await.unwrap().state, "SP");
+        assert!(matches!(api.get_city_and_states_by_ddd(&10).await, Err(Error::InvalidInputRangeError { .. })));
+
+        let holidays = api.get_holidays(&2026).await.unwrap();
+        assert_eq!(holidays.iter().map(|h| h.name.as_str()).collect::<Vec<_>>(), vec!["Confraternização mundial", "Natal"]);
+        assert!(matches!(api.get_holidays(&2025).await, Err(Error::NotFoundError { .. })));
+
+        assert!(api.get_pix_participants().await.unwrap().is_empty());
+    }
+
+    #[async_test]
+    async fn test_higher_level_operations() {
+        let api = fake().with_pix_participant(serde_json::from_str(
+            r#"{"ispb":"00000000","nome":"Banco do Brasil S.A.","nome_reduzido":"BCO DO BRASIL S.A.","modalidade_participacao":"PDCT","tipo_participacao":"DRCT","inicio_operacao":"2020-11-03T09:30:00.000Z"}"#
+        ).unwrap());
+        let ispb: Ispb = "00000000".parse().unwrap();
+
+        let best_effort = api.get_cep_best_effort("01402000").await.unwrap();
+        assert!(!best_effort.is_fallback());
+        let phone: PhoneNumber = "(11) 98765-4321".parse().unwrap();
+        assert_eq!(api.validate_contact("01402-000", &phone).await.unwrap().verdict, ContactVerdict::Match);
+        assert_eq!(phone.lookup(&api).await.unwrap().state, "SP");
+        assert_eq!(api.get_holidays_range(&2026, &2026).await.unwrap().len(), 2);
+        assert_eq!(api.get_bank_by_ispb(&ispb).await.unwrap().code, Some(1));
+        assert_eq!(api.get_pix_institution(&ispb).await.unwrap().bank.and_then(|bank| bank.code), Some(1));
+        assert_eq!(BankDirectory::fetch(&api).await.unwrap().by_code(1).and_then(|bank| bank.ispb.clone()), Some(ispb));
+    }
+
+    #[async_test]
+    async fn test_online_calendar() {
+        let api = fake();
+        let calendar = OnlineBusinessCalendar::new(api.clone(), WeekendRule::default());
+
+        assert!(!calendar.is_business_day(chrono::NaiveDate::from_ymd(2026, 12, 25)).await.unwrap());
+        assert_eq!(
+            calendar.next_business_day(chrono::NaiveDate::from_ymd(2026, 12, 24)).await.unwrap(),
+            chrono::NaiveDate::from_ymd(2026, 12, 28)
+        );
+        assert_eq!(api.calls(EndpointFamily::Holidays), 1);
+    }
+
+    #[async_test]
+    async fn test_failures_and_calls() {
+        let api = fake().with_failure(EndpointFamily::Cep, Error::NotExpectedRequestError);
+        let clone = api.clone();
+
+        assert!(matches!(clone.get_cep("01402000", None).await, Err(Error::NotExpectedRequestError)));
+        assert!(matches!(clone.get_cep("", None).await, Err(Error::InvalidInputLenError { .. })));
+        assert!(clone.get_banks().await.is_ok());
+
+        assert_eq!(api.calls(EndpointFamily::Cep), 1);
+        assert_eq!(api.calls(EndpointFamily::Banks), 1);
+        assert_eq!(api.calls(EndpointFamily::Ddd), 0);
+    }
+}
@@ -0,0 +1,412 @@
+/*!
+Boleto (bank slip) parsing and validation, following the FEBRABAN layouts <br />
+Accepts the 44 digits barcode, the 47 digits linha digitável of bank slips and the 48 digits
+linha digitável of arrecadação (utilities, taxes) slips, with or without the usual dots and spaces
+*/
+use crate::{banks::BankResponseData, api::BrasilApi, errors::Error};
+use chrono::{Duration, Local, NaiveDate};
+
+/// Day 0 of the due factor
+const DUE_FACTOR_BASE: (i32, u32, u32) = (1997, 10, 7);
+/// The due factor goes from 1000 to 9999 and then starts over at 1000 (first rollover on 2025-02-22)
+const DUE_FACTOR_CYCLE: i64 = 9000;
+/// The currency digit of slips in Real
+pub const CURRENCY_REAL: u8 = 9;
+
+/// Why a boleto was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoletoError {
+    /// Something else than digits, spaces, dots and hyphens
+    InvalidCharacter(char),
+    /// Not 44 (barcode), 47 (bank slip line) or 48 (arrecadação line) digits
+    InvalidLength(usize),
+    /// A check digit doesn't match (`field` is "barcode" for the general one, "field N" on the linha digitável)
+    InvalidCheckDigit { field: String },
+    /// A 48 digits line or a barcode starting with 8 that is not an arrecadação slip
+    InvalidValueIdentifier(u8),
+}
+
+impl std::fmt::Display for BoletoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoletoError::InvalidCharacter(c) => write!(f, "Invalid character [{}] on boleto", c),
+            BoletoError::InvalidLength(len) => write!(f, "Boleto should have 44, 47 or 48 digits, found {}", len),
+            BoletoError::InvalidCheckDigit { field } => write!(f, "Invalid check digit on boleto {}", field),
+            BoletoError::InvalidValueIdentifier(id) => write!(f, "Invalid arrecadação value identifier {}", id),
+        }
+    }
+}
+
+impl std::error::Error for BoletoError {}
+
+/// A parsed and validated boleto
+#[derive(Debug, Clone, PartialEq)]
+pub enum Boleto {
+    /// Bank slip ("boleto bancário / de cobrança")
+    Bank(BankSlip),
+    /// Arrecadação / convênio slip (utilities, taxes), the barcode starts with 8
+    Collection(CollectionSlip),
+}
+
+impl Boleto {
+    /// Parses a barcode or linha digitável, resolving the due date against today
+    pub fn parse(input: &str) -> Result<Boleto, BoletoError> {
+        Boleto::parse_at(input, Local::now().naive_local().date())
+    }
+
+    /**
+    Parses a barcode or linha digitável <br />
+    The due factor repeats every 9000 days, so the due date is the one closest to `reference`
+    (usually the day the slip is being paid)
+    */
+    pub fn parse_at(input: &str, reference: NaiveDate) -> Result<Boleto, BoletoError> {
+        let digits = digits(input)?;
+        match digits.len() {
+            44 if digits.starts_with('8') => Ok(Boleto::Collection(CollectionSlip::from_barcode(digits)?)),
+            44 => Ok(Boleto::Bank(BankSlip::from_barcode(digits, reference)?)),
+            47 => Ok(Boleto::Bank(BankSlip::from_line(&digits, reference)?)),
+            48 => Ok(Boleto::Collection(CollectionSlip::from_line(&digits)?)),
+            len => Err(BoletoError::InvalidLength(len)),
+        }
+    }
+
+    /// The 44 digits barcode
+    pub fn barcode(&self) -> &str {
+        match self {
+            Boleto::Bank(slip) => slip.barcode(),
+            Boleto::Collection(slip) => slip.barcode(),
+        }
+    }
+
+    /// The amount in cents, `None` for "any amount" bank slips and reference (non monetary) arrecadação amounts
+    pub fn amount(&self) -> Option<u64> {
+        match self {
+            Boleto::Bank(slip) => Some(slip.amount).filter(|amount| *amount > 0),
+            Boleto::Collection(slip) if slip.amount_kind == AmountKind::Effective => Some(slip.amount),
+            Boleto::Collection(_) => None,
+        }
+    }
+}
+
+/// A bank slip, 44 digits barcode or 47 digits linha digitável
+#[derive(Debug, Clone, PartialEq)]
+pub struct BankSlip {
+    /// The validated 44 digits barcode, private so `linha_digitavel` can rely on it
+    barcode: String,
+    /// The COMPE code of the issuing bank
+    pub bank_code: i16,
+    /// `CURRENCY_REAL` (9) or 0 for other currencies
+    pub currency: u8,
+    /// `None` when the slip has no due date (factor 0)
+    pub due_date: Option<NaiveDate>,
+    /// Amount in cents, 0 when the payer chooses it
+    pub amount: u64,
+    /// The 25 digits defined by each bank
+    pub free_field: String,
+}
+
+impl BankSlip {
+    fn from_barcode(barcode: String, reference: NaiveDate) -> Result<BankSlip, BoletoError> {
+        let without_dv = format!("{}{}", &barcode[..4], &barcode[5..]);
+        if mod11_bank(&without_dv) != digit(&barcode, 4) {
+            return Err(BoletoError::InvalidCheckDigit { field: "barcode".to_string() });
+        }
+
+        Ok(BankSlip {
+            bank_code: barcode[..3].parse().unwrap(),
+            currency: digit(&barcode, 3),
+            due_date: due_date(barcode[5..9].parse().unwrap(), reference),
+            amount: barcode[9..19].parse().unwrap(),
+            free_field: barcode[19..].to_string(),
+            barcode,
+        })
+    }
+
+    fn from_line(line: &str, reference: NaiveDate) -> Result<BankSlip, BoletoError> {
+        for (number, (start, end)) in [(0, 9), (10, 20), (21, 31)].iter().enumerate() {
+            if mod10(&line[*start..*end]) != digit(line, *end) {
+                return Err(BoletoError::InvalidCheckDigit { field: format!("field {}", number + 1) });
+            }
+        }
+
+        let barcode = [&line[0..4], &line[32..47], &line[4..9], &line[10..20], &line[21..31]].concat();
+        BankSlip::from_barcode(barcode, reference)
+    }
+
+    /// The 44 digits barcode
+    pub fn barcode(&self) -> &str {
+        &self.barcode
+    }
+
+    /// The 47 digits linha digitável, without formatting
+    pub fn linha_digitavel(&self) -> String {
+        let b = &self.barcode;
+        let fields = [[&b[0..4], &b[19..24]].concat(), b[24..34].to_string(), b[34..44].to_string()];
+        let mut line = String::with_capacity(47);
+        for field in &fields {
+            line.push_str(field);
+            line.push_str(&mod10(field).to_string());
+        }
+        line.push_str(&b[4..19]);
+        line
+    }
+
+    /// The issuing bank, through `get_banks_by_code` (so answered by the bank directory when it's loaded)
+    pub async fn bank(&self, client: &dyn BrasilApi) -> Result<BankResponseData, Error> {
+        client.get_banks_by_code(&self.bank_code).await
+    }
+}
+
+/// What the amount of an arrecadação slip means
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmountKind {
+    /// Value identifiers 6 and 8: the amount, in cents
+    Effective,
+    /// Value identifiers 7 and 9: a reference (i.e: a quantity or an index), not money
+    Reference,
+}
+
+/// An arrecadação / convênio slip, 44 digits barcode (starting with 8) or 48 digits linha digitável
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionSlip {
+    /// The validated 44 digits barcode, private so `linha_digitavel` can rely on it
+    barcode: String,
+    /// 1 - Prefeituras, 2 - Saneamento, 3 - Energia e gás, 4 - Telecomunicações, 5 - Órgãos governamentais, 6 - Carnês (CNPJ), 7 - Trânsito, 9 - Uso exclusivo do banco
+    pub segment: u8,
+    pub amount_kind: AmountKind,
+    /// 11 digits, cents when `amount_kind` is `Effective`
+    pub amount: u64,
+    /// The company / agency code (4 digits), or the first 8 digits of the CNPJ on segment 6
+    pub company: String,
+    /// The remaining digits, defined by each company
+    pub free_field: String,
+}
+
+impl CollectionSlip {
+    fn from_barcode(barcode: String) -> Result<CollectionSlip, BoletoError> {
+        let check = check_digit_function(digit(&barcode, 2))?;
+        let without_dv = format!("{}{}", &barcode[..3], &barcode[4..]);
+        if check(&without_dv) != digit(&barcode, 3) {
+            return Err(BoletoError::InvalidCheckDigit { field: "barcode".to_string() });
+        }
+
+        let segment = digit(&barcode, 1);
+        let company_end = if segment == 6 { 23 } else { 19 };
+        Ok(CollectionSlip {
+            segment,
+            amount_kind: if matches!(digit(&barcode, 2), 6 | 8) { AmountKind::Effective } else { AmountKind::Reference },
+            amount: barcode[4..15].parse().unwrap(),
+            company: barcode[15..company_end].to_string(),
+            free_field: barcode[company_end..].to_string(),
+            barcode,
+        })
+    }
+
+    fn from_line(line: &str) -> Result<CollectionSlip, BoletoError> {
+        if !line.starts_with('8') {
+            return Err(BoletoError::InvalidValueIdentifier(digit(line, 0)));
+        }
+        let check = check_digit_function(digit(line, 2))?;
+
+        let mut barcode = String::with_capacity(44);
+        for number in 0..4 {
+            let block = &line[number * 12..number * 12 + 11];
+            if check(block) != digit(line, number * 12 + 11) {
+                return Err(BoletoError::InvalidCheckDigit { field: format!("field {}", number + 1) });
+            }
+            barcode.push_str(block);
+        }
+        CollectionSlip::from_barcode(barcode)
+    }
+
+    /// The 44 digits barcode
+    pub fn barcode(&self) -> &str {
+        &self.barcode
+    }
+
+    /// The 48 digits linha digitável, without formatting
+    pub fn linha_digitavel(&self) -> String {
+        let check = check_digit_function(digit(&self.barcode, 2)).unwrap();
+        let mut line = String::with_capacity(48);
+        for number in 0..4 {
+            let block = &self.barcode[number * 11..(number + 1) * 11];
+            line.push_str(block);
+            line.push_str(&check(block).to_string());
+        }
+        line
+    }
+}
+
+/// The due date of a bank slip due factor, `None` for factor 0 (no due date)
+fn due_date(factor: i64, reference: NaiveDate) -> Option<NaiveDate> {
+    if factor == 0 {
+        return None;
+    }
+    let (year, month, day) = DUE_FACTOR_BASE;
+    let first = NaiveDate::from_ymd(year, month, day) + Duration::days(factor);
+
+    // Every cycle since the first one, picking the closest to the reference date
+    let cycles = ((reference - first).num_days() as f64 / DUE_FACTOR_CYCLE as f64).round().max(0.0) as i64;
+    Some(first + Duration::days(cycles * DUE_FACTOR_CYCLE))
+}
+
+/// The value identifier (3rd digit) of arrecadação slips selects the check digit algorithm
+fn check_digit_function(value_identifier: u8) -> Result<fn(&str) -> u8, BoletoError> {
+    match value_identifier {
+        6 | 7 => Ok(mod10),
+        8 | 9 => Ok(mod11_collection),
+        other => Err(BoletoError::InvalidValueIdentifier(other)),
+    }
+}
+
+fn digits(input: &str) -> Result<String, BoletoError> {
+    input
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '.' | '-' | '\t'))
+        .map(|c| if c.is_ascii_digit() { Ok(c) } else { Err(BoletoError::InvalidCharacter(c)) })
+        .collect()
+}
+
+fn digit(digits: &str, index: usize) -> u8 {
+    digits.as_bytes()[index] - b'0'
+}
+
+/// Weights 2, 1, 2, 1... from the right, summing the digits of each product
+fn mod10(digits: &str) -> u8 {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            let product = u32::from(b - b'0') * if i % 2 == 0 { 2 } else { 1 };
+            product / 10 + product % 10
+        })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+/// Weights 2 to 9 from the right, starting over after 9
+fn mod11_sum(digits: &str) -> u32 {
+    digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| u32::from(b - b'0') * (2 + i as u32 % 8))
+        .sum()
+}
+
+/// Bank slips general check digit: 0, 10 and 11 become 1
+fn mod11_bank(digits: &str) -> u8 {
+    match 11 - mod11_sum(digits) % 11 {
+        0 | 10 | 11 => 1,
+        dv => dv as u8,
+    }
+}
+
+/// Arrecadação check digits: remainders 0 and 1 become 0
+fn mod11_collection(digits: &str) -> u8 {
+    match mod11_sum(digits) % 11 {
+        0 | 1 => 0,
+        remainder => (11 - remainder) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::{mock_server, MockResponse};
+    use futures_await_test::async_test;
+
+    const BARCODE: &str = "00196101600000123450000001234567890123456717";
+    const LINE: &str = "00190.00009 01234.567897 01234.567178 6 10160000012345";
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(y, m, d)
+    }
+
+    fn bank_slip(input: &str, reference: NaiveDate) -> BankSlip {
+        match Boleto::parse_at(input, reference).unwrap() {
+            Boleto::Bank(slip) => slip,
+            other => panic!("Expected a bank slip, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bank_slip() {
+        let slip = bank_slip(BARCODE, date(2025, 3, 1));
+        assert_eq!(slip.bank_code, 1);
+        assert_eq!(slip.currency, CURRENCY_REAL);
+        assert_eq!(slip.amount, 12345);
+        assert_eq!(slip.free_field, "0000001234567890123456717");
+        assert_eq!(slip.linha_digitavel(), LINE.replace(|c: char| !c.is_ascii_digit(), ""));
+
+        assert_eq!(bank_slip(LINE, date(2025, 3, 1)), slip);
+    }
+
+    #[test]
+    fn test_due_factor_rollover() {
+        // Factor 1016: 2000-07-19 on the first cycle, 2025-03-10 after the rollover of 2025-02-22
+        assert_eq!(bank_slip(BARCODE, date(2025, 3, 1)).due_date, Some(date(2025, 3, 10)));
+        assert_eq!(bank_slip(BARCODE, date(2000, 7, 1)).due_date, Some(date(2000, 7, 19)));
+        assert_eq!(due_date(9999, date(2025, 2, 1)), Some(date(2025, 2, 21)));
+        assert_eq!(due_date(1000, date(2025, 2, 1)), Some(date(2025, 2, 22)));
+        assert_eq!(due_date(1000, date(2010, 1, 1)), Some(date(2000, 7, 3)));
+        assert_eq!(due_date(0, date(2025, 2, 1)), None);
+    }
+
+    #[test]
+    fn test_collection_slips() {
+        let mod10_line = "836800000017 234500012021 403150000004 000000123455";
+        let mod11_line = "83840000001-4 23450001202-1 40315000000-6 00000012345-5";
+
+        for (line, barcode) in [
+            (mod10_line, "83680000001234500012024031500000000000012345"),
+            (mod11_line, "83840000001234500012024031500000000000012345"),
+        ].iter() {
+            let boleto = Boleto::parse(line).unwrap();
+            assert_eq!(boleto.barcode(), *barcode);
+            assert_eq!(boleto.amount(), Some(12345));
+            assert_eq!(Boleto::parse(barcode).unwrap(), boleto);
+
+            match boleto {
+                Boleto::Collection(slip) => {
+                    assert_eq!(slip.segment, 3);
+                    assert_eq!(slip.company, "0001");
+                    assert_eq!(slip.linha_digitavel(), line.replace(|c: char| !c.is_ascii_digit(), ""));
+                },
+                other => panic!("Expected a collection slip, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(Boleto::parse("123"), Err(BoletoError::InvalidLength(3)));
+        assert_eq!(Boleto::parse(&BARCODE.replace('7', "x")), Err(BoletoError::InvalidCharacter('x')));
+
+        // Amount changed, general check digit doesn't match anymore
+        let tampered = BARCODE.replacen("12345", "12346", 1);
+        assert_eq!(Boleto::parse(&tampered), Err(BoletoError::InvalidCheckDigit { field: "barcode".to_string() }));
+
+        let tampered = LINE.replacen("01234.567897", "01234.567898", 1);
+        assert_eq!(Boleto::parse(&tampered), Err(BoletoError::InvalidCheckDigit { field: "field 2".to_string() }));
+
+        let tampered = "836800000018 234500012021 403150000004 000000123455";
+        assert_eq!(Boleto::parse(tampered), Err(BoletoError::InvalidCheckDigit { field: "field 1".to_string() }));
+        assert_eq!(
+            Boleto::parse("835800000017 234500012021 403150000004 000000123455"),
+            Err(BoletoError::InvalidValueIdentifier(5))
+        );
+    }
+
+    #[async_test]
+    async fn test_bank_resolution() {
+        let server = mock_server(|path| match path {
+            "/banks/v1/1" => MockResponse::json(200, r#"{"ispb":"00000000","name":"BCO DO BRASIL S.A.","code":1,"fullName":"Banco do Brasil S.A."}"#),
+            _ => MockResponse::json(404, r#"{"message":"Código bancário não encontrado","type":"BANK_CODE_NOT_FOUND"}"#),
+        });
+
+        let bank = bank_slip(BARCODE, date(2025, 3, 1)).bank(&server.client()).await.unwrap();
+        assert_eq!(bank.ispb.unwrap().as_str(), "00000000");
+    }
+}
@@ -0,0 +1,366 @@
+use crate::{api::BrasilApi, errors::Error, holidays::HolidaysResponseData};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+/// How far stepping looks for a business day before giving up (i.e: a `Custom` weekend with all 7 days)
+const MAX_STEP_DAYS: i64 = 366;
+/// The most business days `add_business_days` moves, about the 300 years BrasilApi has holidays for
+const MAX_BUSINESS_DAYS: u64 = 300 * 366;
+
+/// Which days of the week are not business days
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum WeekendRule {
+    /// Saturday and Sunday
+    #[default]
+    SaturdayAndSunday,
+    /// Only Sunday (i.e: businesses open on Saturdays)
+    SundayOnly,
+    /// Any set of days
+    Custom(Vec<Weekday>),
+}
+
+impl WeekendRule {
+    pub fn is_weekend(&self, weekday: Weekday) -> bool {
+        match self {
+            WeekendRule::SaturdayAndSunday => weekday == Weekday::Sat || weekday == Weekday::Sun,
+            WeekendRule::SundayOnly => weekday == Weekday::Sun,
+            WeekendRule::Custom(days) => days.contains(&weekday),
+        }
+    }
+}
+
+/**
+Offline business day math, built from one or more years of holidays <br />
+Dates on years that were never loaded are only checked against the weekend rule, see `has_year`
+*/
+#[derive(Debug, Clone, Default)]
+pub struct BusinessCalendar {
+    weekend: WeekendRule,
+    holidays: BTreeMap<NaiveDate, String>,
+    years: BTreeSet<i32>,
+}
+
+impl BusinessCalendar {
+    pub fn new(weekend: WeekendRule) -> BusinessCalendar {
+        BusinessCalendar {
+            weekend,
+            ..BusinessCalendar::default()
+        }
+    }
+
+    /// Builds the calendar with the default weekend, loading every year found on `holidays`
+    pub fn from_holidays<'a>(holidays: impl IntoIterator<Item = &'a HolidaysResponseData>) -> BusinessCalendar {
+        let mut calendar = BusinessCalendar::default();
+        for holiday in holidays {
+            calendar.years.insert(holiday.date.year());
+            calendar.holidays.insert(holiday.date, holiday.name.clone());
+        }
+        calendar
+    }
+
+    /// Fetches the holidays of each year and builds the calendar with them
+    pub async fn fetch(client: &dyn BrasilApi, years: impl IntoIterator<Item = i32>) -> Result<BusinessCalendar, Error> {
+        let mut calendar = BusinessCalendar::default();
+        for year in years {
+            let holidays = client.get_holidays(&year).await?;
+            calendar.add_year(year, &holidays);
+        }
+        Ok(calendar)
+    }
+
+    pub fn with_weekend_rule(self, weekend: WeekendRule) -> BusinessCalendar {
+        BusinessCalendar { weekend, ..self }
+    }
+
+    /// Loads the holidays of `year` (as returned by `get_holidays`), marking it as known even when empty
+    pub fn add_year(&mut self, year: i32, holidays: &[HolidaysResponseData]) {
+        self.years.insert(year);
+        for holiday in holidays {
+            self.holidays.insert(holiday.date, holiday.name.clone());
+        }
+    }
+
+    /// Whether the holidays of `year` were loaded
+    pub fn has_year(&self, year: i32) -> bool {
+        self.years.contains(&year)
+    }
+
+    /// The holiday name, if `date` is a holiday
+    pub fn holiday(&self, date: NaiveDate) -> Option<&str> {
+        self.holidays.get(&date).map(String::as_str)
+    }
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !self.weekend.is_weekend(date.weekday()) && !self.holidays.contains_key(&date)
+    }
+
+    /**
+    The first business day after `date` <br />
+    Fails with `Error::InvalidDataError` when there's none within a year (every weekday is weekend, or a holiday)
+    */
+    pub fn next_business_day(&self, date: NaiveDate) -> Result<NaiveDate, Error> {
+        self.step(date, 1)
+    }
+
+    /// The last business day before `date`, failing like `next_business_day`
+    pub fn previous_business_day(&self, date: NaiveDate) -> Result<NaiveDate, Error> {
+        self.step(date, -1)
+    }
+
+    /**
+    Moves `days` business days from `date` (backwards when negative), failing like `next_business_day`,
+    and with `Error::InvalidDataError` beyond `MAX_BUSINESS_DAYS` <br />
+    Example: friday + 1 business day = next monday
+    */
+    pub fn add_business_days(&self, date: NaiveDate, days: i64) -> Result<NaiveDate, Error> {
+        if days.unsigned_abs() > MAX_BUSINESS_DAYS {
+            return Err(Error::InvalidDataError {
+                message: format!("Can't move {} business days, the limit is {}", days, MAX_BUSINESS_DAYS),
+            });
+        }
+        let direction = if days < 0 { -1 } else { 1 };
+        (0..days.unsigned_abs()).try_fold(date, |current, _| self.step(current, direction))
+    }
+
+    /**
+    How many business days there are after `from` up to `to` (inclusive) <br />
+    Negative when `to` comes before `from`, so `add_business_days(from, business_days_between(from, to))`
+    lands on `to` whenever `to` is a business day
+    */
+    pub fn business_days_between(&self, from: NaiveDate, to: NaiveDate) -> i64 {
+        if to < from {
+            return -self.business_days_between(to, from);
+        }
+
+        let mut count = 0;
+        let mut current = from;
+        while current < to {
+            current += Duration::days(1);
+            if self.is_business_day(current) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    fn step(&self, date: NaiveDate, direction: i64) -> Result<NaiveDate, Error> {
+        let mut current = date;
+        for _ in 0..MAX_STEP_DAYS {
+            current = match current.checked_add_signed(Duration::days(direction)) {
+                Some(next) => next,
+                None => break,
+            };
+            if self.is_business_day(current) {
+                return Ok(current);
+            }
+        }
+        Err(Error::InvalidDataError {
+            message: format!("No business day within {} days of {}", MAX_STEP_DAYS, date),
+        })
+    }
+
+    fn missing_years(&self, from: NaiveDate, to: NaiveDate) -> Vec<i32> {
+        let (from, to) = if from <= to { (from, to) } else { (to, from) };
+        (from.year()..=to.year()).filter(|year| !self.has_year(*year)).collect()
+    }
+}
+
+/**
+A `BusinessCalendar` that fetches (and caches) the holidays of each year it needs <br />
+It can be shared between tasks, the years are loaded only once (one task loads, the others wait for it)
+*/
+pub struct OnlineBusinessCalendar {
+    client: Arc<dyn BrasilApi>,
+    calendar: Mutex<BusinessCalendar>,
+    /// Held while loading years, so concurrent callers missing the same year don't all request it
+    loading: futures::lock::Mutex<()>,
+}
+
+impl OnlineBusinessCalendar {
+    /// `client` is any `BrasilApi` (i.e: a `BrasilApiClient`, or a `FakeBrasilApi` in tests)
+    pub fn new(client: impl BrasilApi + 'static, weekend: WeekendRule) -> OnlineBusinessCalendar {
+        OnlineBusinessCalendar {
+            client: Arc::new(client),
+            calendar: Mutex::new(BusinessCalendar::new(weekend)),
+            loading: futures::lock::Mutex::new(()),
+        }
+    }
+
+    /// A copy of everything loaded so far
+    pub fn snapshot(&self) -> BusinessCalendar {
+        self.calendar.lock().unwrap().clone()
+    }
+
+    async fn load_years(&self, years: Vec<i32>) -> Result<(), Error> {
+        for year in years {
+            let holidays = self.client.get_holidays(&year).await?;
+            self.calendar.lock().unwrap().add_year(year, &holidays);
+        }
+        Ok(())
+    }
+
+    /// Runs `f` on the calendar, loading the missing years between `from` and whatever date `f` reaches
+    async fn resolve<T>(&self, from: NaiveDate, f: impl Fn(&BusinessCalendar) -> (T, NaiveDate)) -> Result<T, Error> {
+        loop {
+            let (missing, value) = {
+                let calendar = self.calendar.lock().unwrap();
+                let (value, reached) = f(&calendar);
+                (calendar.missing_years(from, reached), value)
+            };
+            if missing.is_empty() {
+                return Ok(value);
+            }
+
+            let _loading = self.loading.lock().await;
+            // Another task may have loaded them while this one waited
+            let missing: Vec<i32> = {
+                let calendar = self.calendar.lock().unwrap();
+                missing.into_iter().filter(|year| !calendar.has_year(*year)).collect()
+            };
+            self.load_years(missing).await?;
+        }
+    }
+
+    pub async fn is_business_day(&self, date: NaiveDate) -> Result<bool, Error> {
+        self.resolve(date, |calendar| (calendar.is_business_day(date), date)).await
+    }
+
+    pub async fn next_business_day(&self, date: NaiveDate) -> Result<NaiveDate, Error> {
+        self.resolve(date, |calendar| reached(calendar.next_business_day(date), date)).await?
+    }
+
+    pub async fn previous_business_day(&self, date: NaiveDate) -> Result<NaiveDate, Error> {
+        self.resolve(date, |calendar| reached(calendar.previous_business_day(date), date)).await?
+    }
+
+    pub async fn add_business_days(&self, date: NaiveDate, days: i64) -> Result<NaiveDate, Error> {
+        self.resolve(date, |calendar| reached(calendar.add_business_days(date, days), date)).await?
+    }
+
+    pub async fn business_days_between(&self, from: NaiveDate, to: NaiveDate) -> Result<i64, Error> {
+        self.resolve(from, |calendar| (calendar.business_days_between(from, to), to)).await
+    }
+}
+
+impl std::fmt::Debug for OnlineBusinessCalendar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnlineBusinessCalendar").field("calendar", &self.calendar).finish_non_exhaustive()
+    }
+}
+
+/// The result with the date it reached, `from` when it failed (no year left to load)
+fn reached(result: Result<NaiveDate, Error>, from: NaiveDate) -> (Result<NaiveDate, Error>, NaiveDate) {
+    let date = *result.as_ref().unwrap_or(&from);
+    (result, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::{mock_server, MockResponse};
+    use futures_await_test::async_test;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(y, m, d)
+    }
+
+    fn holidays_2024() -> Vec<HolidaysResponseData> {
+        let text = r#"[{"date":"2024-01-01","name":"Confraternização mundial","type":"national"},{"date":"2024-02-13","name":"Carnaval","type":"national"},{"date":"2024-03-29","name":"Sexta-feira Santa","type":"national"},{"date":"2024-03-31","name":"Páscoa","type":"national"},{"date":"2024-04-21","name":"Tiradentes","type":"national"},{"date":"2024-05-01","name":"Dia do trabalho","type":"national"},{"date":"2024-05-30","name":"Corpus Christi","type":"national"},{"date":"2024-09-07","name":"Independência do Brasil","type":"national"},{"date":"2024-10-12","name":"Nossa Senhora Aparecida","type":"national"},{"date":"2024-11-02","name":"Finados","type":"national"},{"date":"2024-11-15","name":"Proclamação da República","type":"national"},{"date":"2024-11-20","name":"Dia da consciência negra","type":"national"},{"date":"2024-12-25","name":"Natal","type":"national"}]"#;
+        serde_json::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_business_days() {
+        let calendar = BusinessCalendar::from_holidays(&holidays_2024());
+
+        assert!(calendar.has_year(2024));
+        assert!(!calendar.is_business_day(date(2024, 3, 29)));
+        assert!(!calendar.is_business_day(date(2024, 3, 30)));
+        assert!(calendar.is_business_day(date(2024, 4, 1)));
+        assert_eq!(calendar.holiday(date(2024, 12, 25)), Some("Natal"));
+
+        // Thursday before Good Friday
+        assert_eq!(calendar.next_business_day(date(2024, 3, 28)).unwrap(), date(2024, 4, 1));
+        assert_eq!(calendar.previous_business_day(date(2024, 4, 1)).unwrap(), date(2024, 3, 28));
+        assert_eq!(calendar.add_business_days(date(2024, 3, 28), 2).unwrap(), date(2024, 4, 2));
+        assert_eq!(calendar.add_business_days(date(2024, 4, 2), -2).unwrap(), date(2024, 3, 28));
+        assert_eq!(calendar.add_business_days(date(2024, 3, 28), 0).unwrap(), date(2024, 3, 28));
+
+        assert!(matches!(calendar.add_business_days(date(2024, 3, 28), i64::MIN), Err(Error::InvalidDataError { .. })));
+        assert!(matches!(calendar.add_business_days(date(2024, 3, 28), i64::MAX), Err(Error::InvalidDataError { .. })));
+        assert!(matches!(calendar.next_business_day(chrono::naive::MAX_DATE), Err(Error::InvalidDataError { .. })));
+    }
+
+    #[test]
+    fn test_business_days_between() {
+        let calendar = BusinessCalendar::from_holidays(&holidays_2024());
+
+        assert_eq!(calendar.business_days_between(date(2024, 3, 28), date(2024, 4, 2)), 2);
+        assert_eq!(calendar.business_days_between(date(2024, 4, 2), date(2024, 3, 28)), -2);
+        assert_eq!(calendar.business_days_between(date(2024, 1, 1), date(2024, 12, 31)), 254);
+    }
+
+    #[test]
+    fn test_weekend_rules() {
+        let calendar = BusinessCalendar::from_holidays(&holidays_2024()).with_weekend_rule(WeekendRule::SundayOnly);
+        assert!(calendar.is_business_day(date(2024, 3, 30)));
+        assert_eq!(calendar.next_business_day(date(2024, 3, 28)).unwrap(), date(2024, 3, 30));
+
+        let custom = BusinessCalendar::new(WeekendRule::Custom(vec![Weekday::Fri, Weekday::Sat]));
+        assert!(!custom.is_business_day(date(2024, 3, 22)));
+        assert!(custom.is_business_day(date(2024, 3, 24)));
+    }
+
+    #[async_test]
+    async fn test_no_business_day_at_all() {
+        let every_day = WeekendRule::Custom(vec![
+            Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun,
+        ]);
+        let calendar = BusinessCalendar::new(every_day.clone());
+        assert!(matches!(calendar.next_business_day(date(2024, 3, 28)), Err(Error::InvalidDataError { .. })));
+        assert!(matches!(calendar.previous_business_day(date(2024, 3, 28)), Err(Error::InvalidDataError { .. })));
+        assert!(matches!(calendar.add_business_days(date(2024, 3, 28), 3), Err(Error::InvalidDataError { .. })));
+        assert_eq!(calendar.business_days_between(date(2024, 1, 1), date(2024, 12, 31)), 0);
+
+        let server = mock_server(|_| MockResponse::json(200, "[]"));
+        let online = OnlineBusinessCalendar::new(server.client(), every_day);
+        assert!(online.next_business_day(date(2024, 3, 28)).await.is_err());
+    }
+
+    #[async_test]
+    async fn test_online_calendar_fetches_each_year_once() {
+        let server = mock_server(|path| {
+            if path.ends_with("/2024") {
+                MockResponse::json(200, serde_json::to_string(&holidays_2024()).unwrap())
+            } else {
+                MockResponse::json(200, r#"[{"date":"2025-01-01","name":"Confraternização mundial","type":"national"}]"#)
+            }
+        });
+        let calendar = OnlineBusinessCalendar::new(server.client(), WeekendRule::default());
+
+        assert!(!calendar.is_business_day(date(2024, 12, 25)).await.unwrap());
+        assert_eq!(calendar.add_business_days(date(2024, 12, 31), 1).await.unwrap(), date(2025, 1, 2));
+        assert_eq!(calendar.business_days_between(date(2024, 12, 24), date(2025, 1, 2)).await.unwrap(), 5);
+        assert_eq!(server.hits(), 2);
+        assert!(calendar.snapshot().has_year(2025));
+    }
+
+    #[async_test]
+    async fn test_online_calendar_concurrent_callers_share_a_year() {
+        let server = mock_server(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            MockResponse::json(200, serde_json::to_string(&holidays_2024()).unwrap())
+        });
+        // Without coalescing, so only the calendar keeps the requests from repeating
+        let calendar = OnlineBusinessCalendar::new(server.client().with_request_coalescing(false), WeekendRule::default());
+
+        let checks = (1..=5).map(|day| calendar.is_business_day(date(2024, 4, day)));
+        let results = futures::future::join_all(checks).await;
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(server.hits(), 1);
+    }
+}
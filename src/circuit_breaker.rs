@@ -0,0 +1,297 @@
+use crate::client::EndpointFamily;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/**
+Circuit breaker settings <br />
+The circuit opens when, among the latest `window_size` calls (and at least `minimum_calls`),
+the failure rate reaches `failure_rate_threshold`. <br />
+Transport errors and 5xx answers are failures, any other answer (including 4xx) is a success
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Failure rate, between 0.0 and 1.0, that opens the circuit
+    pub failure_rate_threshold: f64,
+    /// Minimum amount of calls on the window before the failure rate is evaluated
+    pub minimum_calls: u32,
+    /// How many of the latest calls are considered
+    pub window_size: u32,
+    /// How long the circuit stays open before letting probes through
+    pub open_interval: Duration,
+    /// How many successful probes are needed to close the circuit again
+    pub half_open_probes: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            failure_rate_threshold: 0.5,
+            minimum_calls: 10,
+            window_size: 20,
+            open_interval: Duration::from_secs(30),
+            half_open_probes: 1,
+        }
+    }
+}
+
+/// The circuit state, as seen by health checks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests fail immediately, for `remaining` more time
+    Open { remaining: Duration },
+    /// A few probe requests are allowed, to check whether the service is back
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { probes_in_flight: u32, successes: u32 },
+}
+
+#[derive(Debug)]
+pub(crate) struct Breaker {
+    config: CircuitBreakerConfig,
+    state: State,
+    window: VecDeque<bool>,
+}
+
+impl Breaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Breaker {
+        Breaker {
+            config,
+            state: State::Closed,
+            window: VecDeque::with_capacity(config.window_size as usize),
+        }
+    }
+
+    /// Asks to send a request, returns how long the circuit stays open when it's denied
+    pub(crate) fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        if let State::Open { until } = self.state {
+            if now < until {
+                return Err(until - now);
+            }
+            self.state = State::HalfOpen { probes_in_flight: 0, successes: 0 };
+        }
+
+        match &mut self.state {
+            State::HalfOpen { probes_in_flight, successes } => {
+                if *probes_in_flight + *successes >= self.config.half_open_probes.max(1) {
+                    return Err(Duration::from_secs(0));
+                }
+                *probes_in_flight += 1;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// A request allowed by `try_acquire` was cancelled before getting an answer
+    pub(crate) fn release(&mut self) {
+        if let State::HalfOpen { probes_in_flight, .. } = &mut self.state {
+            *probes_in_flight = probes_in_flight.saturating_sub(1);
+        }
+    }
+
+    pub(crate) fn record(&mut self, success: bool, now: Instant) {
+        match &mut self.state {
+            State::HalfOpen { probes_in_flight, successes } => {
+                *probes_in_flight = probes_in_flight.saturating_sub(1);
+                if !success {
+                    self.open(now);
+                } else {
+                    *successes += 1;
+                    if *successes >= self.config.half_open_probes.max(1) {
+                        self.state = State::Closed;
+                        self.window.clear();
+                    }
+                }
+            }
+            State::Closed => {
+                if self.window.len() >= self.config.window_size.max(1) as usize {
+                    self.window.pop_front();
+                }
+                self.window.push_back(success);
+
+                if self.window.len() >= self.config.minimum_calls.max(1) as usize
+                    && self.failure_rate() >= self.config.failure_rate_threshold
+                {
+                    self.open(now);
+                }
+            }
+            // Late answers of requests sent before the circuit opened
+            State::Open { .. } => {}
+        }
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.state = State::Open { until: now + self.config.open_interval };
+        self.window.clear();
+    }
+
+    fn failure_rate(&self) -> f64 {
+        if self.window.is_empty() {
+            return 0.0;
+        }
+        self.window.iter().filter(|success| !**success).count() as f64 / self.window.len() as f64
+    }
+
+    pub(crate) fn state(&self, now: Instant) -> CircuitState {
+        match self.state {
+            State::Closed => CircuitState::Closed,
+            State::Open { until } if until > now => CircuitState::Open { remaining: until - now },
+            _ => CircuitState::HalfOpen,
+        }
+    }
+}
+
+/// The per endpoint family breakers, shared between every clone of the client
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CircuitBreakers {
+    default: Option<CircuitBreakerConfig>,
+    configs: HashMap<EndpointFamily, CircuitBreakerConfig>,
+    breakers: Arc<Mutex<HashMap<EndpointFamily, Breaker>>>,
+}
+
+impl CircuitBreakers {
+    pub(crate) fn set_default(&mut self, config: CircuitBreakerConfig) {
+        self.default = Some(config);
+        self.breakers = Arc::default();
+    }
+
+    pub(crate) fn set_family(&mut self, family: EndpointFamily, config: CircuitBreakerConfig) {
+        self.configs.insert(family, config);
+        self.breakers = Arc::default();
+    }
+
+    fn config(&self, family: EndpointFamily) -> Option<CircuitBreakerConfig> {
+        self.configs.get(&family).copied().or(self.default)
+    }
+
+    fn with_breaker<T>(&self, family: EndpointFamily, f: impl FnOnce(&mut Breaker) -> T) -> Option<T> {
+        let config = self.config(family)?;
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(family).or_insert_with(|| Breaker::new(config));
+        Some(f(breaker))
+    }
+
+    /// Returns a permit that must be completed with the request outcome
+    pub(crate) fn acquire(&self, family: EndpointFamily) -> Result<Permit<'_>, Duration> {
+        match self.with_breaker(family, |breaker| breaker.try_acquire(Instant::now())) {
+            Some(Err(remaining)) => Err(remaining),
+            Some(Ok(())) => Ok(Permit { breakers: self, family, active: true }),
+            None => Ok(Permit { breakers: self, family, active: false }),
+        }
+    }
+
+    pub(crate) fn state(&self, family: EndpointFamily) -> Option<CircuitState> {
+        self.with_breaker(family, |breaker| breaker.state(Instant::now()))
+    }
+}
+
+/// A request allowed through the breaker, released on drop if its outcome is never recorded
+pub(crate) struct Permit<'a> {
+    breakers: &'a CircuitBreakers,
+    family: EndpointFamily,
+    active: bool,
+}
+
+impl Permit<'_> {
+    pub(crate) fn record(mut self, success: bool) {
+        if self.active {
+            self.active = false;
+            self.breakers.with_breaker(self.family, |breaker| breaker.record(success, Instant::now()));
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.active {
+            self.breakers.with_breaker(self.family, |breaker| breaker.release());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_rate_threshold: 0.5,
+            minimum_calls: 4,
+            window_size: 4,
+            open_interval: Duration::from_secs(10),
+            half_open_probes: 1,
+        }
+    }
+
+    #[test]
+    fn test_opens_on_failure_rate() {
+        let now = Instant::now();
+        let mut breaker = Breaker::new(config());
+
+        for success in [true, false, true] {
+            assert!(breaker.try_acquire(now).is_ok());
+            breaker.record(success, now);
+        }
+        assert_eq!(breaker.state(now), CircuitState::Closed);
+
+        breaker.record(false, now);
+        assert_eq!(breaker.state(now), CircuitState::Open { remaining: Duration::from_secs(10) });
+        assert_eq!(breaker.try_acquire(now + Duration::from_secs(4)), Err(Duration::from_secs(6)));
+    }
+
+    #[test]
+    fn test_half_open_probe_closes() {
+        let now = Instant::now();
+        let mut breaker = Breaker::new(config());
+        for _ in 0..4 {
+            breaker.record(false, now);
+        }
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(breaker.state(later), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire(later).is_ok());
+        // Only a single probe at once
+        assert!(breaker.try_acquire(later).is_err());
+
+        breaker.record(true, later);
+        assert_eq!(breaker.state(later), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_probe_failure_reopens() {
+        let now = Instant::now();
+        let mut breaker = Breaker::new(config());
+        for _ in 0..4 {
+            breaker.record(false, now);
+        }
+
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.try_acquire(later).is_ok());
+        breaker.record(false, later);
+        assert_eq!(breaker.state(later), CircuitState::Open { remaining: Duration::from_secs(10) });
+    }
+
+    #[test]
+    fn test_cancelled_probe_is_released() {
+        let now = Instant::now();
+        let mut breaker = Breaker::new(config());
+        for _ in 0..4 {
+            breaker.record(false, now);
+        }
+
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.try_acquire(later).is_ok());
+        breaker.release();
+        assert!(breaker.try_acquire(later).is_ok());
+    }
+}
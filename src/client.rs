@@ -1,14 +1,81 @@
+use crate::{
+    banks::directory::BankDirectory,
+    circuit_breaker::{CircuitBreakerConfig, CircuitBreakers, CircuitState},
+    failover::{BaseUrlHealth, BaseUrls, FailoverStrategy},
+    pix::PixParticipant,
+    rate_limit::{RateLimit, RateLimiter},
+    request::Coalescer,
+};
+use std::sync::{Arc, RwLock};
+
+/// The BrasilApi endpoint groups, used to configure per-service behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EndpointFamily {
+    /// banks/v1
+    Banks,
+    /// cep/v1 and cep/v2
+    Cep,
+    /// ddd/v1
+    Ddd,
+    /// feriados/v1
+    Holidays,
+    /// pix/v1
+    Pix,
+}
+
 /// Default client for BrasilApi Operations
 #[derive(Debug)]
 pub struct BrasilApiClient {
-    /// The BaseUrl
-    pub(crate) base_url: String,
+    /// The BaseUrls, in order, with their health
+    pub(crate) base_urls: BaseUrls,
+    /// Client side throttling, shared between clones
+    pub(crate) rate_limiter: RateLimiter,
+    /// In-flight requests, shared between clones
+    pub(crate) coalescer: Coalescer,
+    /// Per endpoint family circuit breakers, shared between clones
+    pub(crate) circuit_breakers: CircuitBreakers,
+    /// Computes the national holidays locally when BrasilApi is unavailable
+    pub(crate) local_holidays_fallback: bool,
+    /// Rejects CEP answers whose state isn't the one of the CEP range
+    pub(crate) cep_state_check: bool,
+    /// Banks directory loaded by `bank_directory`, shared between clones
+    pub(crate) bank_directory: Arc<RwLock<Option<Arc<BankDirectory>>>>,
+    /// PIX participants loaded by `pix_participants`, shared between clones
+    pub(crate) pix_participants: Arc<RwLock<Option<Arc<Vec<PixParticipant>>>>>,
 }
 
 impl BrasilApiClient {
     pub fn new(base_url: impl Into<String>) -> BrasilApiClient {
+        BrasilApiClient::new_with_base_urls(vec![base_url.into()], FailoverStrategy::PrimaryBackup)
+    }
+
+    /**
+    Creates the client with an ordered list of base URLs (i.e: a self-hosted BrasilApi + the public one) <br />
+    On transport errors and 5xx answers the call is retried on the next base URL, which is also kept
+    out of the rotation for a while (see `with_failover_cooldown`) <br />
+    An empty list falls back to the default base URL
+    */
+    pub fn new_with_base_urls<I>(base_urls: I, strategy: FailoverStrategy) -> BrasilApiClient
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut base_urls: Vec<String> = base_urls.into_iter().map(Into::into).collect();
+        if base_urls.is_empty() {
+            log::warn!("No base URL configured, using {}", crate::constants::DEFAULT_BASE_URL);
+            base_urls.push(crate::constants::DEFAULT_BASE_URL.to_string());
+        }
+
         BrasilApiClient {
-            base_url: base_url.into()
+            base_urls: BaseUrls::new(base_urls, strategy),
+            rate_limiter: RateLimiter::default(),
+            coalescer: Coalescer::default(),
+            circuit_breakers: CircuitBreakers::default(),
+            local_holidays_fallback: false,
+            cep_state_check: false,
+            bank_directory: Arc::default(),
+            pix_participants: Arc::default(),
         }
     }
 
@@ -16,17 +83,198 @@ impl BrasilApiClient {
     pub fn new_default() -> BrasilApiClient {
         BrasilApiClient::new(crate::constants::DEFAULT_BASE_URL.to_string())
     }
+
+    /**
+    Limits every request made by this client (and its clones) <br />
+    When the limit is reached, calls wait until a new request is allowed instead of failing
+    */
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> BrasilApiClient {
+        self.rate_limiter.set_global(limit);
+        self
+    }
+
+    /**
+    Limits the requests of a single endpoint family (i.e: CEP separately from banks) <br />
+    It's applied together with the global limit, if any
+    */
+    pub fn with_endpoint_rate_limit(mut self, family: EndpointFamily, limit: RateLimit) -> BrasilApiClient {
+        self.rate_limiter.set_family(family, limit);
+        self
+    }
+
+    /**
+    Concurrent calls for the same URL share a single upstream request, and every caller gets a clone of its result <br />
+    Enabled by default
+    */
+    pub fn with_request_coalescing(mut self, enabled: bool) -> BrasilApiClient {
+        self.coalescer.enabled = enabled;
+        self
+    }
+
+    /**
+    Enables a circuit breaker for every endpoint family, each one tracking its own failures <br />
+    While a circuit is open, calls fail immediately with `Error::CircuitOpen`
+    */
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> BrasilApiClient {
+        self.circuit_breakers.set_default(config);
+        self
+    }
+
+    /// Enables (or overrides the settings of) the circuit breaker of a single endpoint family
+    pub fn with_endpoint_circuit_breaker(mut self, family: EndpointFamily, config: CircuitBreakerConfig) -> BrasilApiClient {
+        self.circuit_breakers.set_family(family, config);
+        self
+    }
+
+    /// How long a base URL stays out of the rotation after failing, 30 seconds by default
+    pub fn with_failover_cooldown(mut self, cooldown: std::time::Duration) -> BrasilApiClient {
+        self.base_urls.cooldown = cooldown;
+        self
+    }
+
+    /// The health of every configured base URL, in the configured order
+    pub fn base_url_health(&self) -> Vec<BaseUrlHealth> {
+        self.base_urls.health()
+    }
+
+    /**
+    When BrasilApi can't be reached, `get_holidays` computes the national holidays locally
+    (see `holidays::local`) instead of failing, except on `local::UNVERIFIED_YEARS`
+    */
+    pub fn with_local_holidays_fallback(mut self, enabled: bool) -> BrasilApiClient {
+        self.local_holidays_fallback = enabled;
+        self
+    }
+
+    /**
+    `get_cep` fails with `Error::InconsistentDataError` when the answered state isn't the one
+    of the CEP range (see `Cep::expected_uf`), disabled by default
+    */
+    pub fn with_cep_state_check(mut self, enabled: bool) -> BrasilApiClient {
+        self.cep_state_check = enabled;
+        self
+    }
+
+    /// The current circuit state of an endpoint family, `None` when it has no circuit breaker
+    pub fn circuit_state(&self, family: EndpointFamily) -> Option<CircuitState> {
+        self.circuit_breakers.state(family)
+    }
 }
 
 impl Clone for BrasilApiClient {
     fn clone(&self) -> Self {
-        Self { base_url: self.base_url.clone() }
+        Self {
+            base_urls: self.base_urls.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            coalescer: self.coalescer.clone(),
+            circuit_breakers: self.circuit_breakers.clone(),
+            local_holidays_fallback: self.local_holidays_fallback,
+            cep_state_check: self.cep_state_check,
+            bank_directory: self.bank_directory.clone(),
+            pix_participants: self.pix_participants.clone(),
+        }
     }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
     use crate::client::BrasilApiClient;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
 
     pub(crate) fn cli() -> BrasilApiClient {BrasilApiClient::new_default()}
-}
\ No newline at end of file
+
+    /// Turns warn logging on, so the logged errors get formatted
+    pub(crate) fn init_logger() {
+        let _ = env_logger::builder().is_test(true).filter_level(log::LevelFilter::Warn).try_init();
+    }
+
+    /// A canned answer for the mock server
+    pub(crate) struct MockResponse {
+        pub(crate) status: u16,
+        pub(crate) headers: Vec<(&'static str, String)>,
+        pub(crate) body: String,
+    }
+
+    impl MockResponse {
+        pub(crate) fn json(status: u16, body: impl Into<String>) -> MockResponse {
+            MockResponse { status, headers: vec![], body: body.into() }
+        }
+
+        pub(crate) fn header(mut self, name: &'static str, value: impl Into<String>) -> MockResponse {
+            self.headers.push((name, value.into()));
+            self
+        }
+    }
+
+    /// Local HTTP server answering every request with `handler(path)`
+    pub(crate) struct MockServer {
+        pub(crate) base_url: String,
+        hits: Arc<AtomicUsize>,
+    }
+
+    impl MockServer {
+        pub(crate) fn hits(&self) -> usize {
+            self.hits.load(Ordering::SeqCst)
+        }
+
+        pub(crate) fn client(&self) -> BrasilApiClient {
+            BrasilApiClient::new(self.base_url.clone())
+        }
+    }
+
+    pub(crate) fn mock_server<F>(handler: F) -> MockServer
+    where
+        F: Fn(&str) -> MockResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+
+        let server_hits = hits.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let handler = handler.clone();
+                server_hits.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf) {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+                    let response = handler(&path);
+                    let mut raw = format!(
+                        "HTTP/1.1 {} MOCK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                        response.status,
+                        response.body.len()
+                    );
+                    for (name, value) in &response.headers {
+                        raw.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    raw.push_str("\r\n");
+                    raw.push_str(&response.body);
+                    let _ = stream.write_all(raw.as_bytes());
+                });
+            }
+        });
+
+        MockServer { base_url, hits }
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 #[serde(untagged)]
 pub enum EmptyOption<T> {
@@ -23,7 +23,7 @@ impl<T> From<Option<T>> for EmptyOption<T> {
     fn from(option: Option<T>) -> EmptyOption<T> {
         match option {
             Some(option) => EmptyOption::Some(option),
-            None {} => EmptyOption::None {},
+            None => EmptyOption::None {},
         }
     }
 }
@@ -39,3 +39,45 @@ impl<T> EmptyOption<T> {
         }
     }
 }
+
+/// The brazilian federative units (states + DF)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Uf {
+    AC, AL, AP, AM, BA, CE, DF, ES, GO, MA, MT, MS, MG, PA,
+    PB, PR, PE, PI, RJ, RN, RS, RO, RR, SC, SP, SE, TO,
+}
+
+impl Uf {
+    pub const ALL: [Uf; 27] = [
+        Uf::AC, Uf::AL, Uf::AP, Uf::AM, Uf::BA, Uf::CE, Uf::DF, Uf::ES, Uf::GO, Uf::MA, Uf::MT, Uf::MS, Uf::MG, Uf::PA,
+        Uf::PB, Uf::PR, Uf::PE, Uf::PI, Uf::RJ, Uf::RN, Uf::RS, Uf::RO, Uf::RR, Uf::SC, Uf::SP, Uf::SE, Uf::TO,
+    ];
+
+    /// The two letters code (i.e: SP)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Uf::AC => "AC", Uf::AL => "AL", Uf::AP => "AP", Uf::AM => "AM", Uf::BA => "BA",
+            Uf::CE => "CE", Uf::DF => "DF", Uf::ES => "ES", Uf::GO => "GO", Uf::MA => "MA",
+            Uf::MT => "MT", Uf::MS => "MS", Uf::MG => "MG", Uf::PA => "PA", Uf::PB => "PB",
+            Uf::PR => "PR", Uf::PE => "PE", Uf::PI => "PI", Uf::RJ => "RJ", Uf::RN => "RN",
+            Uf::RS => "RS", Uf::RO => "RO", Uf::RR => "RR", Uf::SC => "SC", Uf::SP => "SP",
+            Uf::SE => "SE", Uf::TO => "TO",
+        }
+    }
+}
+
+impl std::fmt::Display for Uf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Uf {
+    type Err = ();
+
+    /// Parses the two letters code, ignoring case and surrounding spaces
+    fn from_str(s: &str) -> Result<Uf, ()> {
+        let code = s.trim().to_ascii_uppercase();
+        Uf::ALL.iter().copied().find(|uf| uf.as_str() == code).ok_or(())
+    }
+}
@@ -9,16 +9,70 @@ pub(crate) mod banks {
 pub(crate) mod cep {
     pub(crate) const SVC_V1_URL: &str = "cep/v1";
     pub(crate) const SVC_V2_URL: &str = "cep/v2";
+    /// The Correios CEP ranges of each state, by the first 5 digits (inclusive), sorted
+    pub(crate) const UF_RANGES: &[(u32, u32, crate::commons::Uf)] = {
+        use crate::commons::Uf::*;
+        &[
+            (1000, 19999, SP),
+            (20000, 28999, RJ),
+            (29000, 29999, ES),
+            (30000, 39999, MG),
+            (40000, 48999, BA),
+            (49000, 49999, SE),
+            (50000, 56999, PE),
+            (57000, 57999, AL),
+            (58000, 58999, PB),
+            (59000, 59999, RN),
+            (60000, 63999, CE),
+            (64000, 64999, PI),
+            (65000, 65999, MA),
+            (66000, 68899, PA),
+            (68900, 68999, AP),
+            (69000, 69299, AM),
+            (69300, 69399, RR),
+            (69400, 69899, AM),
+            (69900, 69999, AC),
+            (70000, 72799, DF),
+            (72800, 72999, GO),
+            (73000, 73699, DF),
+            (73700, 76799, GO),
+            (76800, 76999, RO),
+            (77000, 77999, TO),
+            (78000, 78899, MT),
+            (78900, 78999, RO),
+            (79000, 79999, MS),
+            (80000, 87999, PR),
+            (88000, 89999, SC),
+            (90000, 99999, RS),
+        ]
+    };
 }
 
 pub(crate) mod ddd {
   pub(crate) const SVC_URL: &str = "ddd/v1";
   pub(crate) const MIN_DDD: &i8 = &11;
   pub(crate) const MAX_DDD: &i8 = &99;
+  /// The DDDs in use, per Anatel
+  pub(crate) const VALID_DDDS: &[i8] = &[
+    11, 12, 13, 14, 15, 16, 17, 18, 19,
+    21, 22, 24, 27, 28,
+    31, 32, 33, 34, 35, 37, 38,
+    41, 42, 43, 44, 45, 46, 47, 48, 49,
+    51, 53, 54, 55,
+    61, 62, 63, 64, 65, 66, 67, 68, 69,
+    71, 73, 74, 75, 77, 79,
+    81, 82, 83, 84, 85, 86, 87, 88, 89,
+    91, 92, 93, 94, 95, 96, 97, 98, 99,
+  ];
 }
 
 pub(crate) mod holidays {
   pub(crate) const SVC_URL: &str = "feriados/v1";
   pub(crate) const MIN_YEAR: &i32 = &1900;
   pub(crate) const MAX_YEAR: &i32 = &2199;
-}
\ No newline at end of file
+  /// Years requested at once by `get_holidays_range`
+  pub(crate) const RANGE_CONCURRENCY: usize = 4;
+}
+pub(crate) mod pix {
+  pub(crate) const SVC_URL: &str = "pix/v1/participants";
+}
@@ -1,36 +1,84 @@
-use crate::{client::*, constants::banks::*, errors::*, request::*};
-use serde::{Deserialize, Serialize};
+use crate::{client::*, constants::banks::*, errors::*};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{convert::TryFrom, sync::Arc};
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-/// The Banks data struct
-pub struct BankResponseData {
-    /// The ISPB code
-    pub ispb: String,
-    /// The bank Name
-    pub name: String,
-    /// The bank Code
-    pub code: i16,
-    /// The bank FULL_NAME
-    pub full_name: String,
+/// Indexed banks list, with lookups by code / ISPB and name search
+pub mod directory;
+
+use directory::BankDirectory;
+
+/**
+The ISPB, 8 digits identifying every participant of the brazilian payment system <br />
+Unlike the COMPE code, every institution has one (i.e: "00000000" - Banco do Brasil)
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct Ispb(String);
+
+impl Ispb {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for Ispb {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::InvalidDataError { message: format!("Invalid ispb [{}], it should only have digits", value) });
+        }
+        if value.len() != 8 {
+            return Err(Error::InvalidInputLenError { name: "ispb".to_string(), min: 8, max: 8 });
+        }
+        Ok(Ispb(value.to_string()))
+    }
+}
+
+impl TryFrom<String> for Ispb {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Ispb> for String {
+    fn from(value: Ispb) -> Self {
+        value.0
+    }
 }
 
-impl PartialEq for BankResponseData {
-    fn eq(&self, other: &Self) -> bool {
-        self.ispb == other.ispb && self.name == other.name && self.code == other.code && self.full_name == other.full_name
+impl std::fmt::Display for Ispb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// The Banks data struct
+pub struct BankResponseData {
+    /// The ISPB code, `None` when BrasilApi sent a malformed one (which is logged instead of failing the whole list)
+    #[serde(default, deserialize_with = "lenient_ispb")]
+    pub ispb: Option<Ispb>,
+    /// The bank Name, missing for a few participants
+    pub name: Option<String>,
+    /// The bank Code, only banks on the COMPE have one (payment institutions and most cooperatives don't)
+    pub code: Option<i16>,
+    /// The bank FULL_NAME, missing for a few participants
+    pub full_name: Option<String>,
+}
+
 impl BrasilApiClient {
     /// Get all banks
     pub async fn get_banks(&self) -> Result<Vec<BankResponseData>, Error> {
-        Ok(get::<(), Vec<BankResponseData>>(
-            &format!("{}/{}", self.base_url, SVC_URL)
-        ).await?)
+        self.call::<Vec<BankResponseData>>(EndpointFamily::Banks, SVC_URL).await
     }
 
     /**
     Get Banks by code <br />
+    Answered from the cached `bank_directory` when it's loaded and knows the code, from BrasilApi otherwise <br />
     Example: 33 - Santander
     */
     pub async fn get_banks_by_code(&self, code: &i16) -> Result<BankResponseData, Error> {
@@ -43,17 +91,83 @@ impl BrasilApiClient {
                 })
         }
 
-        
-        Ok(get::<(), BankResponseData>(
-            &format!("{}/{}/{}", self.base_url, SVC_URL, code)
-        ).await?)
+        if let Some(bank) = self.cached_bank_directory().and_then(|directory| directory.by_code(*code).cloned()) {
+            return Ok(bank);
+        }
+
+        self.call::<BankResponseData>(
+            EndpointFamily::Banks,
+            &format!("{}/{}", SVC_URL, code)
+        ).await
+    }
+
+    /**
+    The banks directory cached on this client (and its clones), fetched on the first call <br />
+    See `refresh_bank_directory` to reload it
+    */
+    pub async fn bank_directory(&self) -> Result<Arc<BankDirectory>, Error> {
+        match self.cached_bank_directory() {
+            Some(directory) => Ok(directory),
+            None => self.refresh_bank_directory().await,
+        }
+    }
+
+    /// Fetches the banks list again and replaces the cached directory
+    pub async fn refresh_bank_directory(&self) -> Result<Arc<BankDirectory>, Error> {
+        let directory = Arc::new(BankDirectory::fetch(self).await?);
+        *self.bank_directory.write().unwrap() = Some(directory.clone());
+        Ok(directory)
+    }
+
+    /**
+    Get a bank by ISPB, the only identifier every participant has <br />
+    BrasilApi has no such endpoint, so it's resolved through the cached `bank_directory`,
+    which is refreshed when it doesn't know the ISPB (i.e: a participant newer than the cache)
+    */
+    pub async fn get_bank_by_ispb(&self, ispb: &Ispb) -> Result<BankResponseData, Error> {
+        if let Some(bank) = self.cached_bank_directory().and_then(|directory| directory.by_ispb(ispb).cloned()) {
+            return Ok(bank);
+        }
+
+        self.refresh_bank_directory()
+            .await?
+            .by_ispb(ispb)
+            .cloned()
+            .ok_or_else(|| ispb_not_found(ispb))
     }
+
+    fn cached_bank_directory(&self) -> Option<Arc<BankDirectory>> {
+        self.bank_directory.read().unwrap().clone()
+    }
+}
+
+/// A malformed ISPB becomes `None`, so a single bad entry doesn't fail the whole banks list
+fn lenient_ispb<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Ispb>, D::Error> {
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(ispb)) => match ispb.parse() {
+            Ok(ispb) => Some(ispb),
+            Err(e) => {
+                log::warn!("Ignoring the bank ISPB: {}", e);
+                None
+            },
+        },
+        Some(other) => {
+            log::warn!("Ignoring the bank ISPB [{}], it should be a string", other);
+            None
+        },
+    })
+}
+
+pub(crate) fn ispb_not_found(ispb: &Ispb) -> Error {
+    Error::NotFoundError { name: "ispb".to_string(), value: ispb.to_string() }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::client::tests::cli;
+    use crate::client::tests::{cli, mock_server, MockResponse};
     use futures_await_test::async_test;
 
     #[async_test]
@@ -89,4 +203,106 @@ mod tests {
         assert!(resp.is_err());
     }
 
-}
\ No newline at end of file
+    #[async_test]
+    async fn test_get_banks_by_code_from_directory() {
+        let server = mock_server(|path| match path {
+            "/banks/v1" => MockResponse::json(200, directory::tests::BANKS),
+            _ => MockResponse::json(200, r#"{"ispb":"18236120","name":"NU PAGAMENTOS - IP","code":260,"fullName":"NU PAGAMENTOS S.A. - INSTITUIÇÃO DE PAGAMENTO"}"#),
+        });
+        let client = server.client();
+
+        let directory = client.clone().bank_directory().await.unwrap();
+        assert_eq!(directory.len(), 6);
+        assert_eq!(server.hits(), 1);
+
+        // Cached, shared between clones
+        assert_eq!(client.bank_directory().await.unwrap().len(), 6);
+        assert_eq!(client.get_banks_by_code(&33).await.unwrap().ispb.unwrap().as_str(), "90400888");
+        assert_eq!(server.hits(), 1);
+
+        // Unknown to the directory, asks BrasilApi
+        assert_eq!(client.get_banks_by_code(&260).await.unwrap().ispb.unwrap().as_str(), "18236120");
+        assert_eq!(server.hits(), 2);
+
+        client.refresh_bank_directory().await.unwrap();
+        assert_eq!(server.hits(), 3);
+    }
+
+    #[test]
+    fn test_ispb() {
+        assert_eq!("00000000".parse::<Ispb>().unwrap().as_str(), "00000000");
+        assert!(matches!("0000000".parse::<Ispb>(), Err(Error::InvalidInputLenError { .. })));
+        assert!(matches!("0000000A".parse::<Ispb>(), Err(Error::InvalidDataError { .. })));
+        assert!(matches!("0000-000".parse::<Ispb>(), Err(Error::InvalidDataError { .. })));
+    }
+
+    #[test]
+    fn test_malformed_ispb_keeps_the_list() {
+        let text = r#"[
+            {"ispb":"00000000","name":"BCO DO BRASIL S.A.","code":1,"fullName":"Banco do Brasil S.A."},
+            {"ispb":"123","name":"X","code":2,"fullName":"X"},
+            {"ispb":12345678,"name":"Y","code":3,"fullName":"Y"},
+            {"ispb":null,"name":"Z","code":4,"fullName":"Z"},
+            {"name":"W","code":5,"fullName":"W"}
+        ]"#;
+        let banks = serde_json::from_str::<Vec<BankResponseData>>(text).unwrap();
+
+        assert_eq!(banks.len(), 5);
+        assert_eq!(banks[0].ispb.as_ref().map(Ispb::as_str), Some("00000000"));
+        assert!(banks[1..].iter().all(|bank| bank.ispb.is_none()));
+
+        let round_trip: BankResponseData = serde_json::from_str(&serde_json::to_string(&banks[0]).unwrap()).unwrap();
+        assert_eq!(round_trip, banks[0]);
+    }
+
+    #[test]
+    fn test_participants_without_code() {
+        let text = r#"[
+            {"ispb":"00000000","name":"BCO DO BRASIL S.A.","code":1,"fullName":"Banco do Brasil S.A."},
+            {"ispb":"00038121","name":"Selic","code":null,"fullName":"Banco Central do Brasil - Selic"},
+            {"ispb":"00038166","name":null,"code":null,"fullName":null}
+        ]"#;
+        let banks = serde_json::from_str::<Vec<BankResponseData>>(text).unwrap();
+
+        assert_eq!(banks[1].code, None);
+        assert_eq!(banks[2].name, None);
+    }
+
+    #[async_test]
+    async fn test_get_bank_by_ispb() {
+        let server = mock_server(|_| MockResponse::json(200, directory::tests::BANKS));
+        let client = server.client();
+
+        let bank = client.get_bank_by_ispb(&"00360305".parse().unwrap()).await.unwrap();
+        assert_eq!(bank.code, Some(104));
+
+        assert_eq!(client.get_bank_by_ispb(&"00000000".parse().unwrap()).await.unwrap().code, Some(1));
+        assert_eq!(server.hits(), 1);
+
+        // Unknown to the cached directory, which is refreshed before giving up
+        let missing = client.get_bank_by_ispb(&"12345678".parse().unwrap()).await;
+        assert!(matches!(missing, Err(Error::NotFoundError { .. })));
+        assert_eq!(server.hits(), 2);
+    }
+
+    #[async_test]
+    async fn test_get_bank_by_ispb_refreshes_a_stale_directory() {
+        let listed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let server_listed = listed.clone();
+        let server = mock_server(move |_| {
+            let mut banks: Vec<BankResponseData> = serde_json::from_str(directory::tests::BANKS).unwrap();
+            if server_listed.load(std::sync::atomic::Ordering::SeqCst) {
+                banks.push(serde_json::from_str(r#"{"ispb":"12345678","name":"NOVO BANCO","code":null,"fullName":null}"#).unwrap());
+            }
+            MockResponse::json(200, serde_json::to_string(&banks).unwrap())
+        });
+        let client = server.client();
+        assert_eq!(client.bank_directory().await.unwrap().len(), 6);
+
+        listed.store(true, std::sync::atomic::Ordering::SeqCst);
+        let bank = client.get_bank_by_ispb(&"12345678".parse().unwrap()).await.unwrap();
+        assert_eq!(bank.name.as_deref(), Some("NOVO BANCO"));
+        assert_eq!(client.bank_directory().await.unwrap().len(), 7);
+        assert_eq!(server.hits(), 2);
+    }
+}
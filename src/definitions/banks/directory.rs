@@ -0,0 +1,175 @@
+/*!
+In-memory index of the banks list, for lookups without scanning (or calling BrasilApi) every time <br />
+The search ignores accents, case and punctuation, and tolerates small typos ("santnder" finds Santander)
+*/
+use crate::{banks::{BankResponseData, Ispb}, api::BrasilApi, errors::Error, text::{normalize, similarity}};
+use std::collections::HashMap;
+
+/// Below this similarity a word doesn't count as a typo of another
+const MIN_WORD_SIMILARITY: f64 = 0.75;
+
+/// The banks list, indexed by COMPE code and ISPB
+#[derive(Debug, Clone, Default)]
+pub struct BankDirectory {
+    banks: Vec<BankResponseData>,
+    by_code: HashMap<i16, usize>,
+    by_ispb: HashMap<Ispb, usize>,
+    /// The normalized words of `name` + `full_name`, per bank
+    words: Vec<Vec<String>>,
+}
+
+impl BankDirectory {
+    pub fn new(banks: Vec<BankResponseData>) -> BankDirectory {
+        let mut directory = BankDirectory::default();
+        directory.index(banks);
+        directory
+    }
+
+    /// Builds the directory from `get_banks`
+    pub async fn fetch(client: &dyn BrasilApi) -> Result<BankDirectory, Error> {
+        Ok(BankDirectory::new(client.get_banks().await?))
+    }
+
+    /// Reloads the list from BrasilApi, keeping the current one if the call fails
+    pub async fn refresh(&mut self, client: &dyn BrasilApi) -> Result<(), Error> {
+        let banks = client.get_banks().await?;
+        self.index(banks);
+        Ok(())
+    }
+
+    fn index(&mut self, banks: Vec<BankResponseData>) {
+        self.by_code = banks.iter().enumerate().filter_map(|(i, bank)| bank.code.map(|code| (code, i))).collect();
+        self.by_ispb = banks.iter().enumerate().filter_map(|(i, bank)| bank.ispb.clone().map(|ispb| (ispb, i))).collect();
+        self.words = banks
+            .iter()
+            .map(|bank| {
+                let names = [&bank.name, &bank.full_name];
+                let mut words: Vec<String> = names
+                    .iter()
+                    .filter_map(|name| name.as_deref())
+                    .flat_map(|name| normalize(name).split(' ').map(str::to_string).collect::<Vec<_>>())
+                    .filter(|word| !word.is_empty())
+                    .collect();
+                words.sort();
+                words.dedup();
+                words
+            })
+            .collect();
+        self.banks = banks;
+    }
+
+    /// Every bank, in the order BrasilApi returned them
+    pub fn banks(&self) -> &[BankResponseData] {
+        &self.banks
+    }
+
+    pub fn len(&self) -> usize {
+        self.banks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.banks.is_empty()
+    }
+
+    /// Lookup by COMPE code (i.e: 33 - Santander), participants without code are only found by ISPB
+    pub fn by_code(&self, code: i16) -> Option<&BankResponseData> {
+        self.by_code.get(&code).map(|i| &self.banks[*i])
+    }
+
+    /// Lookup by ISPB (i.e: "00000000" - Banco do Brasil)
+    pub fn by_ispb(&self, ispb: &Ispb) -> Option<&BankResponseData> {
+        self.by_ispb.get(ispb).map(|i| &self.banks[*i])
+    }
+
+    /**
+    Banks whose name or full name contain every word of `query`, best matches first <br />
+    A query word matches a name word when it's equal, a prefix of it, or a small typo of it <br />
+    Example: "itau" and "ITAÚ UNIBANCO S.A." match, "caixa economica" and "CAIXA ECONOMICA FEDERAL" too
+    */
+    pub fn search(&self, query: &str) -> Vec<&BankResponseData> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return vec![];
+        }
+        let query: Vec<&str> = query.split(' ').collect();
+
+        let mut found: Vec<(f64, &BankResponseData)> = self
+            .words
+            .iter()
+            .zip(self.banks.iter())
+            .filter_map(|(words, bank)| {
+                let mut total = 0.0;
+                for term in &query {
+                    let best = words.iter().map(|word| word_score(term, word)).fold(0.0, f64::max);
+                    if best == 0.0 {
+                        return None;
+                    }
+                    total += best;
+                }
+                Some((total / query.len() as f64, bank))
+            })
+            .collect();
+
+        found.sort_by(|(a, bank_a), (b, bank_b)| b.partial_cmp(a).unwrap().then_with(|| bank_a.code.cmp(&bank_b.code)));
+        found.into_iter().map(|(_, bank)| bank).collect()
+    }
+}
+
+/// How well a query word matches a name word, 0.0 when it doesn't
+fn word_score(term: &str, word: &str) -> f64 {
+    if term == word {
+        1.0
+    } else if word.starts_with(term) {
+        0.9
+    } else {
+        let score = similarity(term, word);
+        if score >= MIN_WORD_SIMILARITY { score * 0.8 } else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const BANKS: &str = r#"[
+        {"ispb":"00000000","name":"BCO DO BRASIL S.A.","code":1,"fullName":"Banco do Brasil S.A."},
+        {"ispb":"00360305","name":"CAIXA ECONOMICA FEDERAL","code":104,"fullName":"CAIXA ECONOMICA FEDERAL"},
+        {"ispb":"60701190","name":"ITAÚ UNIBANCO S.A.","code":341,"fullName":"ITAÚ UNIBANCO S.A."},
+        {"ispb":"90400888","name":"BCO SANTANDER (BRASIL) S.A.","code":33,"fullName":"Banco Santander (Brasil) S.A."},
+        {"ispb":"60746948","name":"BCO BRADESCO S.A.","code":237,"fullName":"Banco Bradesco S.A."},
+        {"ispb":"18236120","name":"NU PAGAMENTOS - IP","code":null,"fullName":null}
+    ]"#;
+
+    pub(crate) fn directory() -> BankDirectory {
+        BankDirectory::new(serde_json::from_str(BANKS).unwrap())
+    }
+
+    #[test]
+    fn test_lookups() {
+        let directory = directory();
+        assert_eq!(directory.len(), 6);
+        assert_eq!(directory.by_code(33).unwrap().ispb.as_ref().unwrap().as_str(), "90400888");
+        assert_eq!(directory.by_ispb(&"00360305".parse().unwrap()).unwrap().code, Some(104));
+        assert_eq!(directory.by_ispb(&"18236120".parse().unwrap()).unwrap().name.as_deref(), Some("NU PAGAMENTOS - IP"));
+        assert!(directory.by_code(999).is_none());
+        assert!(directory.by_ispb(&"12345678".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_search() {
+        let directory = directory();
+        let codes = |query: &str| directory.search(query).iter().filter_map(|bank| bank.code).collect::<Vec<_>>();
+
+        assert_eq!(codes("itau"), vec![341]);
+        assert_eq!(codes("ITAÚ"), vec![341]);
+        assert_eq!(codes("caixa econômica"), vec![104]);
+        assert_eq!(codes("santnder"), vec![33]);
+        assert_eq!(codes("brad"), vec![237]);
+        // Same score on both, ties by code
+        assert_eq!(codes("banco brasil"), vec![1, 33]);
+        assert_eq!(codes("banco do brasil"), vec![1]);
+        assert_eq!(directory.search("nu pagamentos")[0].ispb.as_ref().unwrap().as_str(), "18236120");
+        assert!(codes("nubank").is_empty());
+        assert!(codes("  ").is_empty());
+    }
+}
@@ -1,9 +1,13 @@
-use crate::{client::*, constants::cep::{SVC_V1_URL, SVC_V2_URL}, errors::*, request::*, commons::EmptyOption};
+use crate::{api::BrasilApi, client::*, constants::cep::{SVC_V1_URL, SVC_V2_URL, UF_RANGES}, errors::*, commons::{EmptyOption, Uf}};
+use futures::{Future, FutureExt, Stream, StreamExt};
+use log::warn;
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::TryFrom};
 
 /**
 The Desired CEP Search Version
 */
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum EnumCepRequestVersion {
     /// V1 for common data, without GeoLocalization
     V1,
@@ -11,44 +15,119 @@ pub enum EnumCepRequestVersion {
     V2
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Geo utilities: distances, bounding boxes and `geo-types` / GeoJSON conversions
+pub mod geo;
+
+/// GeoJSON FeatureCollection export
+pub mod export;
+
+/// Address normalization, similarity scores and mailing labels
+pub mod address;
+
+/// CEP providers other than BrasilApi (ViaCEP, OpenCEP) and chains of providers
+pub mod provider;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 #[serde(rename_all = "camelCase")]
 /// The coordinates in Latitude & Longitude for this address
 pub struct Coordinates {
-    /// The Latitude
-    pub latitude: String,
-    /// The Longitude
-    pub longitude: String
+    /// The Latitude, BrasilApi sends it as a string
+    #[serde(deserialize_with = "serde_coordinate::deserialize")]
+    pub latitude: f64,
+    /// The Longitude, BrasilApi sends it as a string
+    #[serde(deserialize_with = "serde_coordinate::deserialize")]
+    pub longitude: f64
 }
 
-impl PartialEq for Coordinates {
-    fn eq(&self, other: &Self) -> bool {
-        self.latitude == other.latitude && self.longitude == other.longitude
+// Latitudes and longitudes come either as numbers or as strings
+mod serde_coordinate {
+    use serde::{de::Error, Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub(super) enum NumberOrString {
+        Number(f64),
+        Text(String),
+    }
+
+    impl NumberOrString {
+        /// `None` for empty strings
+        pub(super) fn value<E: Error>(self) -> Result<Option<f64>, E> {
+            match self {
+                NumberOrString::Number(number) => Ok(Some(number)),
+                NumberOrString::Text(text) if text.trim().is_empty() => Ok(None),
+                NumberOrString::Text(text) => text.trim().parse().map(Some).map_err(E::custom),
+            }
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        NumberOrString::deserialize(deserializer)?
+            .value()?
+            .ok_or_else(|| D::Error::custom("empty coordinate"))
+    }
+
+    pub(super) fn deserialize_optional<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<NumberOrString>::deserialize(deserializer)? {
+            Some(value) => value.value(),
+            None => Ok(None),
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
+#[serde(from = "RawLocation")]
 /// GeoLocation info
 pub struct Location
 {
-    /// The GeoCoordinates
+    /// The GeoJSON geometry type, "Point"
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    /// The GeoCoordinates, `None` when BrasilApi has none (empty object or empty strings)
     pub coordinates: EmptyOption<Coordinates>
 }
 
 impl Default for Location {
     fn default() -> Self {
-        Location { coordinates: EmptyOption::Some(Coordinates { latitude: String::new(), longitude: String::new() }) }
+        Location { r#type: None, coordinates: EmptyOption::None {} }
     }
 }
 
-impl PartialEq for Location {
-    fn eq(&self, other: &Self) -> bool {
-        self.coordinates.as_option() == other.coordinates.as_option()
+/// The location as BrasilApi sends it, coordinates may be missing, empty or partial
+#[derive(Deserialize)]
+struct RawLocation {
+    #[serde(default, rename = "type")]
+    r#type: Option<String>,
+    #[serde(default)]
+    coordinates: Option<RawCoordinates>,
+}
+
+#[derive(Deserialize)]
+struct RawCoordinates {
+    #[serde(default, deserialize_with = "serde_coordinate::deserialize_optional")]
+    latitude: Option<f64>,
+    #[serde(default, deserialize_with = "serde_coordinate::deserialize_optional")]
+    longitude: Option<f64>,
+}
+
+impl From<RawLocation> for Location {
+    fn from(raw: RawLocation) -> Self {
+        let coordinates = match raw.coordinates {
+            Some(RawCoordinates { latitude: Some(latitude), longitude: Some(longitude) }) => Some(Coordinates { latitude, longitude }),
+            _ => None,
+        };
+        Location { r#type: raw.r#type, coordinates: coordinates.into() }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 /// The Zipcode data struct
 pub struct CepResponseData {
@@ -80,19 +159,54 @@ impl PartialEq for CepResponseData {
     }
 }
 
-impl BrasilApiClient {
-    pub async fn get_cep(&self, cep: &str, cep_version: Option<EnumCepRequestVersion>) -> Result<CepResponseData, Error> {
+/**
+A CEP that can exist: 8 digits inside one of the Correios state ranges <br />
+Parsing ignores punctuation and spaces, "01001-000" and "01001000" are the same CEP, and so is "1001000"
+(spreadsheets drop the leading zero of numeric cells)
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cep(String);
+
+impl Cep {
+    /// The 8 digits
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 01001-000
+    pub fn formatted(&self) -> String {
+        format!("{}-{}", &self.0[..5], &self.0[5..])
+    }
+
+    /// The state the CEP belongs to, by the Correios range table (no request is made)
+    pub fn expected_uf(&self) -> Uf {
+        uf_of(&self.0).expect("a parsed CEP is always inside a range")
+    }
+}
+
+/// The state of the range holding the CEP digits, `None` outside every range
+fn uf_of(digits: &str) -> Option<Uf> {
+    let prefix: u32 = digits.get(..5)?.parse().ok()?;
+    UF_RANGES.iter()
+        .find(|(first, last, _)| (*first..=*last).contains(&prefix))
+        .map(|(_, _, uf)| *uf)
+}
+
+impl std::str::FromStr for Cep {
+    type Err = Error;
+
+    /// `InvalidInputLenError` without 7 or 8 digits, `InvalidInputRangeError` outside the Correios ranges (i.e: 00999-999)
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
         lazy_static! {
             static ref RE: regex::Regex = regex::Regex::new(r"[^0-9]").unwrap();
         }
-        let cepver = cep_version.unwrap_or(EnumCepRequestVersion::V1);
-        let url = match cepver {
-            EnumCepRequestVersion::V2 => SVC_V2_URL,
-            _ => SVC_V1_URL     
-        };
-        
-        let temp_zipcode = RE.replace_all(cep, "");
-        if temp_zipcode.is_empty() || temp_zipcode.len() > 8 {
+        let mut zipcode = RE.replace_all(value, "");
+        // The leading zero lost by a numeric spreadsheet cell
+        if zipcode.len() == 7 {
+            zipcode = format!("0{}", zipcode).into();
+        }
+        if zipcode.len() != 8 {
             return Err(Error::InvalidInputLenError
                 {
                     name: "cep".to_string(),
@@ -100,10 +214,182 @@ impl BrasilApiClient {
                     max: 8
                 })
         }
+        if uf_of(&zipcode).is_none() {
+            return Err(Error::InvalidInputRangeError
+                {
+                    name: "cep".to_string(),
+                    min: 1000000,
+                    max: 99999999
+                })
+        }
+        Ok(Cep(zipcode.into_owned()))
+    }
+}
+
+impl TryFrom<String> for Cep {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Cep> for String {
+    fn from(value: Cep) -> Self {
+        value.0
+    }
+}
+
+impl std::fmt::Display for Cep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl CepResponseData {
+    /// The state this CEP should be in, `None` when the CEP itself is invalid
+    pub fn expected_uf(&self) -> Option<Uf> {
+        self.cep.parse::<Cep>().ok().map(|cep| cep.expected_uf())
+    }
+
+    /**
+    Whether `state` is the one of the CEP range <br />
+    `None` when it can't be told (invalid CEP, or a state that isn't a UF)
+    */
+    pub fn is_state_consistent(&self) -> Option<bool> {
+        let state: Uf = self.state.trim().parse().ok()?;
+        Some(self.expected_uf()? == state)
+    }
+
+    /// `Error::InconsistentDataError` when `state` isn't the one of the CEP range
+    pub fn check_state(&self) -> Result<(), Error> {
+        match (self.is_state_consistent(), self.expected_uf()) {
+            (Some(false), Some(expected)) => Err(Error::InconsistentDataError {
+                name: "state".to_string(),
+                expected: expected.to_string(),
+                found: self.state.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The answer of `get_cep_best_effort`, with where it came from
+#[derive(Debug, Clone)]
+pub struct BestEffortCep {
+    pub data: CepResponseData,
+    /// V2 when the coordinates request worked, V1 after a fallback
+    pub version: EnumCepRequestVersion,
+    /// Why V2 failed, when it did
+    pub v2_error: Option<Error>,
+}
+
+impl BestEffortCep {
+    /// The upstream service BrasilApi got the data from (i.e: "viacep", "correios")
+    pub fn service(&self) -> &str {
+        &self.data.service
+    }
+
+    pub fn is_fallback(&self) -> bool {
+        self.version == EnumCepRequestVersion::V1
+    }
+}
+
+impl BrasilApiClient {
+    pub async fn get_cep(&self, cep: &str, cep_version: Option<EnumCepRequestVersion>) -> Result<CepResponseData, Error> {
+        let cepver = cep_version.unwrap_or(EnumCepRequestVersion::V1);
+        let url = match cepver {
+            EnumCepRequestVersion::V2 => SVC_V2_URL,
+            _ => SVC_V1_URL     
+        };
         
-        Ok(get::<(), CepResponseData>(
-            &format!("{}/{}/{}", self.base_url, url, temp_zipcode)
-        ).await?)
+        let temp_zipcode: Cep = cep.parse()?;
+        
+        let resp = self.call::<CepResponseData>(
+            EndpointFamily::Cep,
+            &format!("{}/{}", url, temp_zipcode)
+        ).await?;
+
+        if self.cep_state_check {
+            resp.check_state()?;
+        }
+        Ok(resp)
+    }
+
+    /**
+    Tries V2 (with coordinates) and falls back to V1 when it fails, so a V2 problem never costs the address <br />
+    Invalid CEPs fail right away, without any request; when both versions fail, the V1 error is returned
+    */
+    pub async fn get_cep_best_effort(&self, cep: &str) -> Result<BestEffortCep, Error> {
+        cep_best_effort(self, cep).await
+    }
+
+    /**
+    Looks up many CEPs, handling at most `concurrency` inputs at once <br />
+    The stream yields one item per input, with the input as given (i.e: to match spreadsheet rows back),
+    but identical CEPs (i.e: "01402-000" and "01402000") are requested only once and share the result <br />
+    Items come in completion order, see `get_ceps_ordered` to keep the input order
+    */
+    pub fn get_ceps<'a, I>(&'a self, ceps: I, concurrency: usize) -> impl Stream<Item = (String, Result<CepResponseData, Error>)> + 'a
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        I::IntoIter: 'a,
+    {
+        self.cep_lookups(ceps).buffer_unordered(concurrency.max(1))
+    }
+
+    /// Same as `get_ceps`, but items come in the same order as the input
+    pub fn get_ceps_ordered<'a, I>(&'a self, ceps: I, concurrency: usize) -> impl Stream<Item = (String, Result<CepResponseData, Error>)> + 'a
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        I::IntoIter: 'a,
+    {
+        self.cep_lookups(ceps).buffered(concurrency.max(1))
+    }
+
+    fn cep_lookups<'a, I>(&'a self, ceps: I) -> impl Stream<Item = impl Future<Output = (String, Result<CepResponseData, Error>)> + 'a> + 'a
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        I::IntoIter: 'a,
+    {
+        let mut lookups = HashMap::new();
+        futures::stream::iter(ceps).map(move |cep| {
+            let input = cep.as_ref().to_string();
+            let key = batch_key(&input);
+            let lookup = lookups.entry(key.clone())
+                .or_insert_with(|| async move { self.get_cep(&key, None).await }.shared())
+                .clone();
+            async move { (input, lookup.await) }
+        })
+    }
+}
+
+/// `get_cep_best_effort` on any `BrasilApi`
+pub(crate) async fn cep_best_effort<A: BrasilApi + ?Sized>(api: &A, cep: &str) -> Result<BestEffortCep, Error> {
+    match api.get_cep(cep, Some(EnumCepRequestVersion::V2)).await {
+        Ok(data) => Ok(BestEffortCep { data, version: EnumCepRequestVersion::V2, v2_error: None }),
+        Err(e @ Error::InvalidInputLenError { .. }) | Err(e @ Error::InvalidInputRangeError { .. }) => Err(e),
+        Err(v2_error) => {
+            warn!("CEP V2 lookup failed, falling back to V1: {}", v2_error);
+            let data = api.get_cep(cep, Some(EnumCepRequestVersion::V1)).await?;
+            Ok(BestEffortCep { data, version: EnumCepRequestVersion::V1, v2_error: Some(v2_error) })
+        },
+    }
+}
+
+/// Normalized CEP used to deduplicate batch lookups (the `Cep` digits when it's valid)
+fn batch_key(cep: &str) -> String {
+    if let Ok(cep) = cep.parse::<Cep>() {
+        return cep.into();
+    }
+    let digits: String = cep.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        cep.to_string()
+    } else {
+        digits
     }
 }
 
@@ -114,6 +400,194 @@ mod tests {
     use crate::client::tests::*;
     use futures_await_test::async_test;
 
+    fn mock_cep_body(cep: &str) -> String {
+        format!(r#"{{"cep":"{}","state":"SP","city":"São Paulo","neighborhood":"Jardim Paulista","street":"Avenida Brigadeiro Luís Antônio","service":"mock"}}"#, cep)
+    }
+
+    #[async_test]
+    async fn test_get_cep_best_effort() {
+        let server = mock_server(|path| match path {
+            "/cep/v2/01402000" => MockResponse::json(200, mock_cep_body("01402000")
+                .replace(r#""service":"mock"}"#, r#""service":"open-cep","location":{"type":"Point","coordinates":{"longitude":"-46.65","latitude":"-23.57"}}}"#)),
+            "/cep/v2/01310100" => MockResponse::json(500, r#"{"name":"internal","message":"boom","type":"internal_error"}"#),
+            path => MockResponse::json(200, mock_cep_body(path.rsplit('/').next().unwrap())),
+        });
+        let cli = server.client();
+
+        let v2 = cli.get_cep_best_effort("01402-000").await.unwrap();
+        assert_eq!(v2.version, EnumCepRequestVersion::V2);
+        assert_eq!(v2.service(), "open-cep");
+        assert!(v2.data.coordinates().is_some());
+        assert!(!v2.is_fallback());
+
+        let v1 = cli.get_cep_best_effort("01310-100").await.unwrap();
+        assert!(v1.is_fallback());
+        assert_eq!(v1.service(), "mock");
+        match &v1.v2_error {
+            Some(Error::FailoverExhausted { attempts }) => assert!(matches!(attempts[0].error, Error::BrasilApiError { .. })),
+            other => panic!("Expected the V2 failure, got {:?}", other),
+        }
+        assert_eq!(server.hits(), 3);
+
+        assert!(cli.get_cep_best_effort("").await.is_err());
+        assert_eq!(server.hits(), 3);
+    }
+
+    #[async_test]
+    async fn test_get_cep_best_effort_html_error() {
+        init_logger();
+        let server = mock_server(|path| match path {
+            "/cep/v2/01402000" => MockResponse::json(502, "<html><body><h1>502 Bad Gateway</h1></body></html>"),
+            path => MockResponse::json(200, mock_cep_body(path.rsplit('/').next().unwrap())),
+        });
+
+        let resp = server.client().get_cep_best_effort("01402-000").await.unwrap();
+        assert!(resp.is_fallback());
+        let v2_error = resp.v2_error.unwrap();
+        match &v2_error {
+            Error::FailoverExhausted { attempts } => assert!(matches!(attempts[0].error, Error::SerdeJsonError(_))),
+            other => panic!("Expected the V2 failure, got {:?}", other),
+        }
+        assert!(v2_error.to_string().contains(&format!("{}: Invalid JSON: ", server.base_url)));
+    }
+
+    #[test]
+    fn test_cep_expected_uf() {
+        let uf = |cep: &str| cep.parse::<Cep>().unwrap().expected_uf();
+        assert_eq!(uf("01001-000"), Uf::SP);
+        assert_eq!(uf("19999-999"), Uf::SP);
+        assert_eq!(uf("20040-020"), Uf::RJ);
+        assert_eq!(uf("69301-000"), Uf::RR);
+        assert_eq!(uf("69400-000"), Uf::AM);
+        assert_eq!(uf("70040-010"), Uf::DF);
+        assert_eq!(uf("72800-000"), Uf::GO);
+        assert_eq!(uf("73000-000"), Uf::DF);
+        assert_eq!(uf("74000-000"), Uf::GO);
+        assert_eq!(uf("76801-000"), Uf::RO);
+        assert_eq!(uf("78900-000"), Uf::RO);
+        assert_eq!(uf("78899-999"), Uf::MT);
+        assert_eq!(uf("99999-999"), Uf::RS);
+
+        // The table covers 01000 to 99999 with no gaps nor overlaps
+        let mut next = 1000;
+        for (first, last, _) in UF_RANGES {
+            assert_eq!(*first, next);
+            next = last + 1;
+        }
+        assert_eq!(next, 100000);
+        assert!(Uf::ALL.iter().all(|uf| UF_RANGES.iter().any(|(_, _, range_uf)| range_uf == uf)));
+    }
+
+    #[test]
+    fn test_cep_parse() {
+        let cep: Cep = " 01001-000 ".parse().unwrap();
+        assert_eq!(cep.as_str(), "01001000");
+        assert_eq!(cep.formatted(), "01001-000");
+        assert_eq!(serde_json::to_string(&cep).unwrap(), r#""01001000""#);
+        assert_eq!(serde_json::from_str::<Cep>(r#""01001-000""#).unwrap(), cep);
+
+        assert!(matches!("00999-999".parse::<Cep>(), Err(Error::InvalidInputRangeError { .. })));
+        assert_eq!("1001000".parse::<Cep>().unwrap(), cep);
+        assert!(matches!("100100".parse::<Cep>(), Err(Error::InvalidInputLenError { .. })));
+        assert!(matches!("010010000".parse::<Cep>(), Err(Error::InvalidInputLenError { .. })));
+        assert!(serde_json::from_str::<Cep>(r#""00000000""#).is_err());
+    }
+
+    #[test]
+    fn test_state_consistency() {
+        let data = |state: &str| -> CepResponseData { serde_json::from_str(&mock_cep_body("01001000").replace(r#""state":"SP""#, &format!(r#""state":"{}""#, state))).unwrap() };
+        assert_eq!(data("SP").is_state_consistent(), Some(true));
+        assert!(data("SP").check_state().is_ok());
+        assert_eq!(data("RJ").is_state_consistent(), Some(false));
+        assert!(matches!(data("RJ").check_state(), Err(Error::InconsistentDataError { .. })));
+        assert_eq!(data("São Paulo").is_state_consistent(), None);
+        assert!(data("São Paulo").check_state().is_ok());
+    }
+
+    #[async_test]
+    async fn test_get_cep_rejects_impossible_ceps() {
+        let server = mock_server(|path| MockResponse::json(200, mock_cep_body(path.rsplit('/').next().unwrap())));
+
+        assert!(matches!(server.client().get_cep("00100-000", None).await, Err(Error::InvalidInputRangeError { .. })));
+        // Padded to 00100100, still outside every range
+        assert!(matches!(server.client().get_cep("0100100", None).await, Err(Error::InvalidInputRangeError { .. })));
+        assert!(matches!(server.client().get_cep("100100", None).await, Err(Error::InvalidInputLenError { .. })));
+        assert_eq!(server.hits(), 0);
+
+        // A spreadsheet CEP without its leading zero
+        assert_eq!(server.client().get_cep("1001000", None).await.unwrap().cep, "01001000");
+
+        // The mock answers SP for a RJ CEP
+        assert!(server.client().get_cep("20040-020", None).await.is_ok());
+        let checked = server.client().with_cep_state_check(true);
+        assert!(checked.get_cep("01001-000", None).await.is_ok());
+        match checked.get_cep("20040-020", None).await {
+            Err(Error::InconsistentDataError { expected, found, .. }) => assert_eq!((expected.as_str(), found.as_str()), ("RJ", "SP")),
+            other => panic!("Expected an inconsistent state, got {:?}", other),
+        }
+    }
+
+    #[async_test]
+    async fn test_get_ceps_deduplicates() {
+        let server = mock_server(|path| {
+            let cep = path.rsplit('/').next().unwrap().to_string();
+            MockResponse::json(200, mock_cep_body(&cep))
+        });
+        let cli = server.client();
+
+        let results: Vec<_> = cli
+            .get_ceps(vec!["01402-000", "01402000", "01310-100", " 01310100 "], 2)
+            .collect()
+            .await;
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(server.hits(), 2);
+        let mut inputs: Vec<&str> = results.iter().map(|(input, _)| input.as_str()).collect();
+        inputs.sort_unstable();
+        assert_eq!(inputs, vec![" 01310100 ", "01310-100", "01402-000", "01402000"]);
+        assert!(results.iter().all(|(input, resp)| resp.as_ref().unwrap().cep == batch_key(input)));
+
+        let ordered: Vec<_> = cli
+            .get_ceps_ordered(vec!["01402-000", "01310-100", "01402000", "01402-000"], 1)
+            .map(|(input, resp)| (input, resp.unwrap().cep))
+            .collect()
+            .await;
+        assert_eq!(ordered, vec![
+            ("01402-000".to_string(), "01402000".to_string()),
+            ("01310-100".to_string(), "01310100".to_string()),
+            ("01402000".to_string(), "01402000".to_string()),
+            ("01402-000".to_string(), "01402000".to_string()),
+        ]);
+    }
+
+    #[async_test]
+    async fn test_get_ceps_ordered_keeps_input_order() {
+        let server = mock_server(|path| {
+            let cep = path.rsplit('/').next().unwrap().to_string();
+            if cep == "01402000" {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            MockResponse::json(200, mock_cep_body(&cep))
+        });
+        let cli = server.client();
+        let input = vec!["01402000", "01310100", "04538133"];
+
+        let ordered: Vec<String> = cli.get_ceps_ordered(input.clone(), 3).map(|(key, _)| key).collect().await;
+        assert_eq!(ordered, input);
+
+        let unordered: Vec<String> = cli.get_ceps(input, 3).map(|(key, _)| key).collect().await;
+        assert_eq!(unordered.last().unwrap(), "01402000");
+    }
+
+    #[async_test]
+    async fn test_get_ceps_reports_invalid_input() {
+        let cli = cli();
+        let results: Vec<_> = cli.get_ceps(vec!["", "123456789"], 2).collect().await;
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, resp)| resp.is_err()));
+    }
+
     #[async_test]
     async fn testc_invalid_input_minlen_none_ver() {
         let resp = cli().get_cep("09777", None)
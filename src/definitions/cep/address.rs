@@ -0,0 +1,251 @@
+/*!
+Comparison of user typed addresses with CEP results, and Correios style mailing labels <br />
+"Av. Brig. Luís Antônio" and "Avenida Brigadeiro Luis Antonio" are the same street once the abbreviations
+are expanded and the accents and case are ignored
+*/
+use crate::{
+    cep::{Cep, CepResponseData},
+    text::{normalize, similarity, strip_accents},
+};
+
+/// Standard logradouro abbreviations (and titles common on street names), without accents or dots
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("al", "Alameda"),
+    ("av", "Avenida"),
+    ("avda", "Avenida"),
+    ("bc", "Beco"),
+    ("bl", "Bloco"),
+    ("brig", "Brigadeiro"),
+    ("cap", "Capitão"),
+    ("cel", "Coronel"),
+    ("cj", "Conjunto"),
+    ("conj", "Conjunto"),
+    ("dep", "Deputado"),
+    ("dr", "Doutor"),
+    ("dra", "Doutora"),
+    ("eng", "Engenheiro"),
+    ("est", "Estrada"),
+    ("estr", "Estrada"),
+    ("gal", "General"),
+    ("gen", "General"),
+    ("gov", "Governador"),
+    ("jd", "Jardim"),
+    ("lgo", "Largo"),
+    ("lrg", "Largo"),
+    ("mal", "Marechal"),
+    ("pc", "Praça"),
+    ("pca", "Praça"),
+    ("pq", "Parque"),
+    ("pres", "Presidente"),
+    ("prof", "Professor"),
+    ("profa", "Professora"),
+    ("rod", "Rodovia"),
+    ("sen", "Senador"),
+    ("sta", "Santa"),
+    ("sto", "Santo"),
+    ("ten", "Tenente"),
+    ("trav", "Travessa"),
+    ("tv", "Travessa"),
+    ("vl", "Vila"),
+];
+
+/// Single letter abbreviations, only expanded as the first word (the logradouro type)
+const TYPE_ABBREVIATIONS: &[(&str, &str)] = &[("r", "Rua"), ("p", "Praça"), ("q", "Quadra")];
+
+/// Words ignored when scoring
+const STOPWORDS: &[&str] = &["a", "o", "as", "os", "da", "das", "de", "do", "dos", "e"];
+
+/**
+Expands the standard abbreviations, keeping everything else (case, accents, numbers, punctuation) as typed <br />
+Example: "Av. Brig. Luís Antônio, 2000" -> "Avenida Brigadeiro Luís Antônio, 2000"
+*/
+pub fn expand_abbreviations(address: &str) -> String {
+    address
+        .split_whitespace()
+        .enumerate()
+        .map(|(index, word)| expand_word(word, index == 0))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn expand_word(word: &str, first: bool) -> String {
+    let core = word.trim_end_matches(['.', ',', ';', ':']);
+    let suffix = word[core.len()..].trim_start_matches('.');
+    let key = strip_accents(core).to_lowercase();
+
+    let expansion = ABBREVIATIONS.iter()
+        .chain(if first { TYPE_ABBREVIATIONS } else { &[] })
+        .find(|(abbreviation, _)| *abbreviation == key)
+        .map(|(_, full)| *full);
+
+    match expansion {
+        Some(full) => format!("{}{}", full, suffix),
+        None => word.to_string(),
+    }
+}
+
+/// Comparison form: abbreviations expanded, no accents, lowercase, no punctuation (i.e: "avenida paulista 1000")
+pub fn normalize_address(address: &str) -> String {
+    normalize(&expand_abbreviations(address))
+}
+
+/**
+How much of `reference` is found in `typed`, from 0.0 to 1.0 <br />
+Each word of `reference` is matched to the most similar typed word (tolerating typos), so extra typed words
+like the house number don't lower the score
+*/
+pub fn address_similarity(typed: &str, reference: &str) -> f64 {
+    let words = |text: &str| -> Vec<String> {
+        normalize_address(text)
+            .split(' ')
+            .filter(|word| !word.is_empty() && !STOPWORDS.contains(word))
+            .map(str::to_string)
+            .collect()
+    };
+    let (typed, reference) = (words(typed), words(reference));
+    if reference.is_empty() {
+        return if typed.is_empty() { 1.0 } else { 0.0 };
+    }
+
+    let total: f64 = reference.iter()
+        .map(|word| typed.iter().map(|candidate| similarity(word, candidate)).fold(0.0, f64::max))
+        .sum();
+    total / reference.len() as f64
+}
+
+/// Similarity of each typed field with the CEP result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AddressScore {
+    pub street: f64,
+    /// `None` when no neighborhood was typed
+    pub neighborhood: Option<f64>,
+    /// `None` when no city was typed
+    pub city: Option<f64>,
+}
+
+impl AddressScore {
+    /// Weighted mean of the typed fields: street 60%, neighborhood and city 20% each
+    pub fn overall(&self) -> f64 {
+        let scores = [(Some(self.street), 0.6), (self.neighborhood, 0.2), (self.city, 0.2)];
+        let (total, weights) = scores.iter()
+            .filter_map(|(score, weight)| score.map(|score| (score * weight, *weight)))
+            .fold((0.0, 0.0), |(total, weights), (score, weight)| (total + score, weights + weight));
+        total / weights
+    }
+}
+
+impl CepResponseData {
+    /// Compares a typed address with this result, see `address_similarity`
+    pub fn compare_address(&self, street: &str, neighborhood: Option<&str>, city: Option<&str>) -> AddressScore {
+        AddressScore {
+            street: address_similarity(street, &self.street),
+            neighborhood: neighborhood.map(|typed| address_similarity(typed, &self.neighborhood)),
+            city: city.map(|typed| address_similarity(typed, &self.city)),
+        }
+    }
+
+    /**
+    Correios style mailing label block, in uppercase and without abbreviations: <br />
+    recipient / street, number - complement / neighborhood / CEP city - UF <br />
+    An empty `number` becomes "S/N", empty street and neighborhood lines (cities with a single CEP) are left out
+    */
+    pub fn mailing_label(&self, recipient: &str, number: &str, complement: Option<&str>) -> String {
+        let mut lines = vec![recipient.trim().to_uppercase()];
+
+        let street = expand_abbreviations(self.street.trim());
+        if !street.is_empty() {
+            let number = match number.trim() {
+                "" => "S/N",
+                number => number,
+            };
+            let mut line = format!("{}, {}", street, number);
+            if let Some(complement) = complement.map(str::trim).filter(|c| !c.is_empty()) {
+                line = format!("{} - {}", line, complement);
+            }
+            lines.push(line.to_uppercase());
+        }
+
+        if !self.neighborhood.trim().is_empty() {
+            lines.push(self.neighborhood.trim().to_uppercase());
+        }
+
+        let cep = self.cep.parse::<Cep>().map(|cep| cep.formatted()).unwrap_or_else(|_| self.cep.clone());
+        let mut line = format!("{} {}", cep, self.city.trim()).trim_end().to_string();
+        if !self.state.trim().is_empty() {
+            line = format!("{} - {}", line, self.state.trim());
+        }
+        lines.push(line.to_uppercase());
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cep(street: &str, neighborhood: &str) -> CepResponseData {
+        serde_json::from_str(&format!(
+            r#"{{"cep":"01402000","state":"SP","city":"São Paulo","neighborhood":"{}","street":"{}","service":"mock"}}"#,
+            neighborhood, street
+        )).unwrap()
+    }
+
+    #[test]
+    fn test_expand_abbreviations() {
+        assert_eq!(expand_abbreviations("Av. Brig. Luís Antônio, 2000"), "Avenida Brigadeiro Luís Antônio, 2000");
+        assert_eq!(expand_abbreviations("R Dr. Arnaldo"), "Rua Doutor Arnaldo");
+        assert_eq!(expand_abbreviations("Pça. da Sé"), "Praça da Sé");
+        assert_eq!(expand_abbreviations("tv.  Sta Rita"), "Travessa Santa Rita");
+        // Single letters only as the logradouro type
+        assert_eq!(expand_abbreviations("Rua R"), "Rua R");
+        assert_eq!(expand_abbreviations("Rua Augusta"), "Rua Augusta");
+    }
+
+    #[test]
+    fn test_normalize_address() {
+        assert_eq!(normalize_address("AV. BRIG. LUÍS ANTÔNIO"), "avenida brigadeiro luis antonio");
+        assert_eq!(normalize_address("Avenida Brigadeiro Luis Antonio"), normalize_address("av brig luís antônio"));
+    }
+
+    #[test]
+    fn test_similarity() {
+        let result = cep("Avenida Brigadeiro Luís Antônio", "Jardim Paulista");
+
+        let exact = result.compare_address("Av. Brig. Luis Antonio, 2000", Some("Jd. Paulista"), Some("sao paulo"));
+        assert_eq!(exact.street, 1.0);
+        assert_eq!(exact.overall(), 1.0);
+
+        let typo = result.compare_address("av brigadero luiz antonio", None, None);
+        assert!(typo.street > 0.8 && typo.street < 1.0, "{}", typo.street);
+        assert_eq!(typo.overall(), typo.street);
+
+        let other = result.compare_address("Rua Augusta", Some("Consolação"), Some("São Paulo"));
+        assert!(other.street < 0.5, "{}", other.street);
+        assert!(other.overall() < exact.overall());
+
+        assert_eq!(address_similarity("", ""), 1.0);
+        assert_eq!(address_similarity("Rua Augusta", ""), 0.0);
+    }
+
+    #[test]
+    fn test_mailing_label() {
+        let result = cep("Av. Brigadeiro Luís Antônio", "Jardim Paulista");
+        assert_eq!(
+            result.mailing_label("Fulano de Tal", "2000", Some("apto 51")),
+            "FULANO DE TAL\nAVENIDA BRIGADEIRO LUÍS ANTÔNIO, 2000 - APTO 51\nJARDIM PAULISTA\n01402-000 SÃO PAULO - SP"
+        );
+        assert_eq!(
+            result.mailing_label("Fulano de Tal", " ", None).lines().nth(1),
+            Some("AVENIDA BRIGADEIRO LUÍS ANTÔNIO, S/N")
+        );
+        assert_eq!(
+            cep("", "").mailing_label("Fulano de Tal", "10", None),
+            "FULANO DE TAL\n01402-000 SÃO PAULO - SP"
+        );
+
+        let mut no_state = cep("", "");
+        no_state.state = " ".to_string();
+        assert_eq!(no_state.mailing_label("Fulano de Tal", "10", None), "FULANO DE TAL\n01402-000 SÃO PAULO");
+    }
+}
@@ -0,0 +1,123 @@
+/*!
+GeoJSON (RFC 7946) export of CEP lookups, to plot the addresses on a map (geojson.io, QGIS, Kepler, etc) <br />
+Only V2 answers with coordinates become features, the others are reported apart
+*/
+use crate::cep::CepResponseData;
+use serde_json::{json, Value};
+
+/// A FeatureCollection and the entries left out of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoJsonExport {
+    /// The `FeatureCollection` object
+    pub collection: Value,
+    /// The entries without coordinates (V1 answers, or CEPs BrasilApi couldn't locate), in the input order
+    pub without_coordinates: Vec<CepResponseData>,
+}
+
+impl GeoJsonExport {
+    /// How many features the collection has
+    pub fn len(&self) -> usize {
+        self.collection["features"].as_array().map_or(0, Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The collection as a GeoJSON document
+    pub fn to_json(&self) -> String {
+        self.collection.to_string()
+    }
+}
+
+/**
+One Point feature per CEP with coordinates, with the address fields as properties and the CEP as `id` <br />
+Example: `{"type":"Feature","id":"01001000","geometry":{"type":"Point","coordinates":[-46.63,-23.55]},"properties":{"cep":"01001000",...}}`
+*/
+pub fn feature_collection<'a>(ceps: impl IntoIterator<Item = &'a CepResponseData>) -> GeoJsonExport {
+    let mut features = vec![];
+    let mut without_coordinates = vec![];
+
+    for cep in ceps {
+        match cep.coordinates() {
+            Some(point) => features.push(json!({
+                "type": "Feature",
+                "id": cep.cep,
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [point.longitude, point.latitude],
+                },
+                "properties": {
+                    "cep": cep.cep,
+                    "state": cep.state,
+                    "city": cep.city,
+                    "neighborhood": cep.neighborhood,
+                    "street": cep.street,
+                    "service": cep.service,
+                },
+            })),
+            None => without_coordinates.push(cep.clone()),
+        }
+    }
+
+    GeoJsonExport {
+        collection: json!({ "type": "FeatureCollection", "features": features }),
+        without_coordinates,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cep(cep: &str, location: &str) -> CepResponseData {
+        serde_json::from_str(&format!(
+            r#"{{"cep":"{}","state":"SP","city":"São Paulo","neighborhood":"Jardim Paulista","street":"Avenida Brigadeiro Luís Antônio","service":"mock"{}}}"#,
+            cep, location
+        )).unwrap()
+    }
+
+    #[test]
+    fn test_feature_collection() {
+        let ceps = vec![
+            cep("01402000", r#","location":{"type":"Point","coordinates":{"longitude":"-46.6573802","latitude":"-23.57555"}}"#),
+            cep("01001000", ""),
+            cep("01310100", r#","location":{"type":"Point","coordinates":{}}"#),
+            cep("01310200", r#","location":{"type":"Point","coordinates":{"longitude":-46.65,"latitude":-23.56}}"#),
+        ];
+
+        let export = feature_collection(&ceps);
+        assert_eq!(export.len(), 2);
+        assert_eq!(
+            export.without_coordinates.iter().map(|c| c.cep.as_str()).collect::<Vec<_>>(),
+            vec!["01001000", "01310100"]
+        );
+
+        let first = &export.collection["features"][0];
+        assert_eq!(export.collection["type"], "FeatureCollection");
+        assert_eq!(first["id"], "01402000");
+        assert_eq!(first["geometry"]["coordinates"], json!([-46.6573802, -23.57555]));
+        assert_eq!(first["properties"]["street"], "Avenida Brigadeiro Luís Antônio");
+
+        let parsed: Value = serde_json::from_str(&export.to_json()).unwrap();
+        assert_eq!(parsed, export.collection);
+    }
+
+    #[test]
+    fn test_empty() {
+        let export = feature_collection(&[]);
+        assert!(export.is_empty());
+        assert_eq!(export.to_json(), r#"{"features":[],"type":"FeatureCollection"}"#);
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn test_valid_geojson() {
+        let ceps = vec![cep("01402000", r#","location":{"type":"Point","coordinates":{"longitude":"-46.6573802","latitude":"-23.57555"}}"#)];
+        let parsed: geojson::GeoJson = feature_collection(&ceps).to_json().parse().unwrap();
+        match parsed {
+            geojson::GeoJson::FeatureCollection(collection) => assert_eq!(collection.features.len(), 1),
+            other => panic!("Expected a FeatureCollection, got {:?}", other),
+        }
+    }
+}
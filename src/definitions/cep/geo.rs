@@ -0,0 +1,210 @@
+/*!
+Geo utilities over the V2 CEP coordinates: great-circle distances and bounding boxes <br />
+With the `geo` feature, coordinates also convert to `geo-types` points and GeoJSON geometries
+*/
+use crate::cep::{CepResponseData, Coordinates};
+use serde::{Deserialize, Serialize};
+
+/// Mean earth radius (IUGG), in km
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+impl Coordinates {
+    pub fn new(latitude: f64, longitude: f64) -> Coordinates {
+        Coordinates { latitude, longitude }
+    }
+
+    /// Great-circle distance (haversine), in km
+    pub fn distance_km(&self, other: &Coordinates) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+impl CepResponseData {
+    /// The coordinates, only available on V2 answers that have them
+    pub fn coordinates(&self) -> Option<&Coordinates> {
+        self.location.coordinates.as_option()
+    }
+
+    /// Distance between both addresses in km, `None` when either has no coordinates
+    pub fn distance_km(&self, other: &CepResponseData) -> Option<f64> {
+        Some(self.coordinates()?.distance_km(other.coordinates()?))
+    }
+
+    /// Whether the address is inside `area`, `false` when it has no coordinates
+    pub fn is_within(&self, area: &BoundingBox) -> bool {
+        self.coordinates().is_some_and(|point| area.contains(point))
+    }
+}
+
+/// A latitude / longitude rectangle (it doesn't cross the antimeridian, which Brazil never does)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    /// Minimum latitude
+    pub south: f64,
+    /// Minimum longitude
+    pub west: f64,
+    /// Maximum latitude
+    pub north: f64,
+    /// Maximum longitude
+    pub east: f64,
+}
+
+impl BoundingBox {
+    /// The box with two opposite corners, in any order
+    pub fn new(a: Coordinates, b: Coordinates) -> BoundingBox {
+        BoundingBox {
+            south: a.latitude.min(b.latitude),
+            west: a.longitude.min(b.longitude),
+            north: a.latitude.max(b.latitude),
+            east: a.longitude.max(b.longitude),
+        }
+    }
+
+    /// The smallest box containing every point, `None` without points
+    pub fn enclosing<'a>(points: impl IntoIterator<Item = &'a Coordinates>) -> Option<BoundingBox> {
+        let mut points = points.into_iter();
+        let first = *points.next()?;
+        Some(points.fold(BoundingBox::new(first, first), |area, point| BoundingBox {
+            south: area.south.min(point.latitude),
+            west: area.west.min(point.longitude),
+            north: area.north.max(point.latitude),
+            east: area.east.max(point.longitude),
+        }))
+    }
+
+    /// A box containing the circle of `radius_km` around `center` (i.e: to pre-filter before `distance_km`)
+    pub fn around(center: &Coordinates, radius_km: f64) -> BoundingBox {
+        let d_lat = (radius_km / EARTH_RADIUS_KM).to_degrees();
+        let d_lon = d_lat / center.latitude.to_radians().cos();
+        BoundingBox {
+            south: center.latitude - d_lat,
+            west: center.longitude - d_lon,
+            north: center.latitude + d_lat,
+            east: center.longitude + d_lon,
+        }
+    }
+
+    /// Borders included
+    pub fn contains(&self, point: &Coordinates) -> bool {
+        (self.south..=self.north).contains(&point.latitude) && (self.west..=self.east).contains(&point.longitude)
+    }
+}
+
+#[cfg(feature = "geo")]
+mod conversions {
+    use super::*;
+
+    impl From<Coordinates> for geo_types::Point<f64> {
+        fn from(coordinates: Coordinates) -> Self {
+            geo_types::Point::new(coordinates.longitude, coordinates.latitude)
+        }
+    }
+
+    impl From<geo_types::Point<f64>> for Coordinates {
+        fn from(point: geo_types::Point<f64>) -> Self {
+            Coordinates::new(point.y(), point.x())
+        }
+    }
+
+    impl From<BoundingBox> for geo_types::Rect<f64> {
+        fn from(area: BoundingBox) -> Self {
+            geo_types::Rect::new(
+                geo_types::coord! { x: area.west, y: area.south },
+                geo_types::coord! { x: area.east, y: area.north },
+            )
+        }
+    }
+
+    /// A GeoJSON Point, `[longitude, latitude]`
+    impl From<Coordinates> for geojson::Geometry {
+        fn from(coordinates: Coordinates) -> Self {
+            geojson::Geometry::new(geojson::Value::Point(vec![coordinates.longitude, coordinates.latitude]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cep::Location, commons::EmptyOption};
+
+    const SE: Coordinates = Coordinates { latitude: -23.5505, longitude: -46.6333 };
+    const RIO: Coordinates = Coordinates { latitude: -22.9068, longitude: -43.1729 };
+
+    fn cep_at(coordinates: Option<Coordinates>) -> CepResponseData {
+        let mut cep: CepResponseData = serde_json::from_str(
+            r#"{"cep":"01001000","state":"SP","city":"São Paulo","neighborhood":"Sé","street":"Praça da Sé","service":"mock"}"#
+        ).unwrap();
+        cep.location = Location { r#type: Some("Point".to_string()), coordinates: coordinates.into() };
+        cep
+    }
+
+    #[test]
+    fn test_location_formats() {
+        let parse = |json: &str| serde_json::from_str::<Location>(json).unwrap();
+
+        let strings = parse(r#"{"type":"Point","coordinates":{"longitude":"-46.6573802","latitude":"-23.57555"}}"#);
+        assert_eq!(strings.r#type.as_deref(), Some("Point"));
+        assert_eq!(strings.coordinates, EmptyOption::Some(Coordinates::new(-23.57555, -46.6573802)));
+
+        let numbers = parse(r#"{"type":"Point","coordinates":{"longitude":-46.6573802,"latitude":-23.57555}}"#);
+        assert_eq!(numbers, strings);
+
+        assert_eq!(parse(r#"{"type":"Point","coordinates":{}}"#).coordinates, EmptyOption::None {});
+        assert_eq!(parse(r#"{"type":"Point","coordinates":{"longitude":"","latitude":""}}"#).coordinates, EmptyOption::None {});
+        assert!(serde_json::from_str::<Location>(r#"{"coordinates":{"longitude":"abc","latitude":"1"}}"#).is_err());
+
+        // V1 answers have no location at all
+        assert_eq!(cep_at(None).location.coordinates, Location::default().coordinates);
+
+        let json = serde_json::to_string(&strings).unwrap();
+        assert_eq!(json, r#"{"type":"Point","coordinates":{"latitude":-23.57555,"longitude":-46.6573802}}"#);
+        assert_eq!(parse(&json), strings);
+    }
+
+    #[test]
+    fn test_distance() {
+        assert!((SE.distance_km(&RIO) - 360.75).abs() < 0.01);
+        assert_eq!(SE.distance_km(&SE), 0.0);
+
+        let paulista = cep_at(Some(Coordinates::new(-23.57555, -46.6573802)));
+        assert!((paulista.distance_km(&cep_at(Some(SE))).unwrap() - 3.71).abs() < 0.01);
+        assert_eq!(paulista.distance_km(&cep_at(None)), None);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let area = BoundingBox::new(RIO, SE);
+        assert_eq!((area.south, area.west, area.north, area.east), (SE.latitude, SE.longitude, RIO.latitude, RIO.longitude));
+        assert!(area.contains(&Coordinates::new(-23.0, -45.0)));
+        assert!(!area.contains(&Coordinates::new(-23.0, -40.0)));
+        assert_eq!(BoundingBox::enclosing(&[SE, RIO]), Some(area));
+        assert_eq!(BoundingBox::enclosing(&[]), None);
+
+        let around = BoundingBox::around(&SE, 10.0);
+        assert!(cep_at(Some(Coordinates::new(-23.57555, -46.6573802))).is_within(&around));
+        assert!(!cep_at(Some(RIO)).is_within(&around));
+        assert!(!cep_at(None).is_within(&around));
+        // The box holds the whole circle
+        assert!(around.contains(&Coordinates::new(SE.latitude, SE.longitude + 0.0975)));
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn test_geo_conversions() {
+        let point: geo_types::Point<f64> = SE.into();
+        assert_eq!((point.x(), point.y()), (SE.longitude, SE.latitude));
+        assert_eq!(Coordinates::from(point), SE);
+
+        let geometry: geojson::Geometry = SE.into();
+        assert_eq!(geometry.value, geojson::Value::Point(vec![SE.longitude, SE.latitude]));
+
+        let rect: geo_types::Rect<f64> = BoundingBox::new(SE, RIO).into();
+        assert_eq!(rect.min().x, SE.longitude);
+    }
+}
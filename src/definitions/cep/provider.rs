@@ -0,0 +1,438 @@
+/*!
+CEP lookups that survive a BrasilApi outage <br />
+`CepProvider` is implemented by the BrasilApi client and by providers calling ViaCEP and OpenCEP directly,
+whose answers are mapped into `CepResponseData` (without coordinates). A `CepProviderChain` tries them in order or races them
+*/
+use crate::{
+    cep::{Cep, CepResponseData, EnumCepRequestVersion, Location},
+    client::BrasilApiClient,
+    errors::{Error, ProviderFailure},
+    request::get,
+};
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+use log::warn;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// ViaCEP public URL
+pub const VIACEP_URL: &str = "https://viacep.com.br/ws";
+/// OpenCEP public URL
+pub const OPENCEP_URL: &str = "https://opencep.com/v1";
+
+/// A service able to resolve a CEP
+#[async_trait]
+pub trait CepProvider: Send + Sync {
+    /// Short name, reported on failures (i.e: "viacep")
+    fn name(&self) -> &str;
+
+    /// Resolves the CEP, given with or without punctuation
+    async fn lookup(&self, cep: &str) -> Result<CepResponseData, Error>;
+}
+
+/// BrasilApi itself, asking V1 (use `BrasilApiProvider` for V2)
+#[async_trait]
+impl CepProvider for BrasilApiClient {
+    fn name(&self) -> &str {
+        "brasilapi"
+    }
+
+    async fn lookup(&self, cep: &str) -> Result<CepResponseData, Error> {
+        self.get_cep(cep, None).await
+    }
+}
+
+/// BrasilApi asking a specific CEP version
+#[derive(Debug, Clone)]
+pub struct BrasilApiProvider {
+    client: BrasilApiClient,
+    version: EnumCepRequestVersion,
+}
+
+impl BrasilApiProvider {
+    pub fn new(client: BrasilApiClient, version: EnumCepRequestVersion) -> BrasilApiProvider {
+        BrasilApiProvider { client, version }
+    }
+}
+
+#[async_trait]
+impl CepProvider for BrasilApiProvider {
+    fn name(&self) -> &str {
+        "brasilapi"
+    }
+
+    async fn lookup(&self, cep: &str) -> Result<CepResponseData, Error> {
+        self.client.get_cep(cep, Some(self.version)).await
+    }
+}
+
+/// The address format shared by ViaCEP and OpenCEP
+#[derive(Deserialize)]
+struct ViaCepAddress {
+    #[serde(default)]
+    cep: String,
+    #[serde(default)]
+    logradouro: String,
+    #[serde(default)]
+    bairro: String,
+    #[serde(default)]
+    localidade: String,
+    #[serde(default)]
+    uf: String,
+    /// ViaCEP answers unknown CEPs with HTTP 200 and `{"erro": true}` (or `"true"`)
+    #[serde(default)]
+    erro: Option<serde_json::Value>,
+}
+
+impl ViaCepAddress {
+    fn into_response(self, cep: &str, service: &str) -> CepResponseData {
+        let digits: String = self.cep.chars().filter(char::is_ascii_digit).collect();
+        CepResponseData {
+            cep: if digits.is_empty() { cep.to_string() } else { digits },
+            state: self.uf,
+            city: self.localidade,
+            neighborhood: self.bairro,
+            street: self.logradouro,
+            service: service.to_string(),
+            location: Location::default(),
+        }
+    }
+}
+
+/// Requests `url` and maps a ViaCEP formatted answer, 400 and 404 mean the CEP doesn't exist
+async fn lookup_viacep_format(provider: &str, url: &str, cep: &str) -> Result<CepResponseData, Error> {
+    let raw = get(url).await?;
+    let not_found = || Error::NotFoundError { name: "cep".to_string(), value: cep.to_string() };
+
+    match raw.status {
+        200..=299 => {
+            let address: ViaCepAddress = serde_json::from_str(&raw.body)?;
+            match &address.erro {
+                Some(serde_json::Value::Bool(true)) => return Err(not_found()),
+                Some(serde_json::Value::String(erro)) if erro == "true" => return Err(not_found()),
+                _ => {},
+            }
+            Ok(address.into_response(cep, provider))
+        },
+        400 | 404 => Err(not_found()),
+        status => Err(Error::ProviderError { provider: provider.to_string(), status, message: raw.body }),
+    }
+}
+
+/// [ViaCEP](https://viacep.com.br), requested directly
+#[derive(Debug, Clone)]
+pub struct ViaCepProvider {
+    base_url: String,
+}
+
+impl Default for ViaCepProvider {
+    fn default() -> Self {
+        ViaCepProvider::new(VIACEP_URL)
+    }
+}
+
+impl ViaCepProvider {
+    /// `base_url` without the trailing slash, i.e: `VIACEP_URL`
+    pub fn new(base_url: impl Into<String>) -> ViaCepProvider {
+        ViaCepProvider { base_url: base_url.into() }
+    }
+}
+
+#[async_trait]
+impl CepProvider for ViaCepProvider {
+    fn name(&self) -> &str {
+        "viacep"
+    }
+
+    async fn lookup(&self, cep: &str) -> Result<CepResponseData, Error> {
+        let cep: Cep = cep.parse()?;
+        lookup_viacep_format(self.name(), &format!("{}/{}/json/", self.base_url, cep), cep.as_str()).await
+    }
+}
+
+/// [OpenCEP](https://opencep.com), requested directly, it answers in the ViaCEP format
+#[derive(Debug, Clone)]
+pub struct OpenCepProvider {
+    base_url: String,
+}
+
+impl Default for OpenCepProvider {
+    fn default() -> Self {
+        OpenCepProvider::new(OPENCEP_URL)
+    }
+}
+
+impl OpenCepProvider {
+    /// `base_url` without the trailing slash, i.e: `OPENCEP_URL`
+    pub fn new(base_url: impl Into<String>) -> OpenCepProvider {
+        OpenCepProvider { base_url: base_url.into() }
+    }
+}
+
+#[async_trait]
+impl CepProvider for OpenCepProvider {
+    fn name(&self) -> &str {
+        "opencep"
+    }
+
+    async fn lookup(&self, cep: &str) -> Result<CepResponseData, Error> {
+        let cep: Cep = cep.parse()?;
+        lookup_viacep_format(self.name(), &format!("{}/{}", self.base_url, cep), cep.as_str()).await
+    }
+}
+
+/// How a `CepProviderChain` uses its providers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainStrategy {
+    /// One at a time, in order, until one answers
+    Sequential,
+    /// All at once, the first answer wins (the others are dropped)
+    Race,
+}
+
+/// Providers tried until one of them resolves the CEP
+#[derive(Clone)]
+pub struct CepProviderChain {
+    providers: Vec<Arc<dyn CepProvider>>,
+    strategy: ChainStrategy,
+}
+
+impl Default for CepProviderChain {
+    fn default() -> Self {
+        CepProviderChain::new(ChainStrategy::Sequential)
+    }
+}
+
+impl std::fmt::Debug for CepProviderChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CepProviderChain")
+            .field("providers", &self.providers.iter().map(|p| p.name()).collect::<Vec<_>>())
+            .field("strategy", &self.strategy)
+            .finish()
+    }
+}
+
+impl CepProviderChain {
+    pub fn new(strategy: ChainStrategy) -> CepProviderChain {
+        CepProviderChain { providers: vec![], strategy }
+    }
+
+    /// BrasilApi first, then ViaCEP and OpenCEP
+    pub fn with_public_providers(client: BrasilApiClient, strategy: ChainStrategy) -> CepProviderChain {
+        CepProviderChain::new(strategy)
+            .with_provider(client)
+            .with_provider(ViaCepProvider::default())
+            .with_provider(OpenCepProvider::default())
+    }
+
+    /// Adds a provider after the current ones
+    pub fn with_provider(mut self, provider: impl CepProvider + 'static) -> CepProviderChain {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: ChainStrategy) -> CepProviderChain {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn strategy(&self) -> ChainStrategy {
+        self.strategy
+    }
+
+    /// The provider names, in order
+    pub fn providers(&self) -> Vec<&str> {
+        self.providers.iter().map(|p| p.name()).collect()
+    }
+
+    async fn sequential(&self, cep: &str) -> Result<CepResponseData, Vec<ProviderFailure>> {
+        let mut attempts = vec![];
+        for provider in &self.providers {
+            match provider.lookup(cep).await {
+                Ok(data) => return Ok(data),
+                Err(error) => {
+                    warn!("CEP provider {} failed, trying the next one: {}", provider.name(), error);
+                    attempts.push(ProviderFailure { provider: provider.name().to_string(), error });
+                },
+            }
+        }
+        Err(attempts)
+    }
+
+    async fn race(&self, cep: &str) -> Result<CepResponseData, Vec<ProviderFailure>> {
+        let mut pending: FuturesUnordered<_> = self.providers.iter()
+            .enumerate()
+            .map(|(index, provider)| async move { (index, provider.lookup(cep).await) })
+            .collect();
+
+        let mut failures = vec![];
+        while let Some((index, resp)) = pending.next().await {
+            match resp {
+                Ok(data) => return Ok(data),
+                Err(error) => failures.push((index, error)),
+            }
+        }
+
+        failures.sort_by_key(|(index, _)| *index);
+        Err(failures.into_iter()
+            .map(|(index, error)| ProviderFailure { provider: self.providers[index].name().to_string(), error })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CepProvider for CepProviderChain {
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    /**
+    Resolves the CEP with the configured strategy <br />
+    Invalid CEPs fail right away; when every provider fails, `Error::ProvidersExhausted` has each failure in the configured order
+    */
+    async fn lookup(&self, cep: &str) -> Result<CepResponseData, Error> {
+        cep.parse::<Cep>()?;
+        let resp = match self.strategy {
+            ChainStrategy::Sequential => self.sequential(cep).await,
+            ChainStrategy::Race => self.race(cep).await,
+        };
+        resp.map_err(|attempts| Error::ProvidersExhausted { attempts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::{init_logger, mock_server, MockResponse};
+    use futures_await_test::async_test;
+
+    const VIACEP_BODY: &str = r#"{"cep":"01001-000","logradouro":"Praça da Sé","complemento":"lado ímpar","bairro":"Sé","localidade":"São Paulo","uf":"SP","ibge":"3550308","gia":"1004","ddd":"11","siafi":"7107"}"#;
+
+    #[async_test]
+    async fn test_viacep() {
+        let server = mock_server(|path| match path {
+            "/01001000/json/" => MockResponse::json(200, VIACEP_BODY),
+            "/99999999/json/" => MockResponse::json(200, r#"{"erro": "true"}"#),
+            _ => MockResponse::json(400, "<h1>Bad Request</h1>"),
+        });
+        let provider = ViaCepProvider::new(server.base_url.clone());
+
+        let data = provider.lookup("01001-000").await.unwrap();
+        assert_eq!(data.cep, "01001000");
+        assert_eq!((data.state.as_str(), data.city.as_str()), ("SP", "São Paulo"));
+        assert_eq!((data.neighborhood.as_str(), data.street.as_str()), ("Sé", "Praça da Sé"));
+        assert_eq!(data.service, "viacep");
+        assert!(data.coordinates().is_none());
+
+        assert!(matches!(provider.lookup("99999-999").await, Err(Error::NotFoundError { .. })));
+        assert!(matches!(provider.lookup("1234").await, Err(Error::InvalidInputLenError { .. })));
+        assert!(matches!(provider.lookup("").await, Err(Error::InvalidInputLenError { .. })));
+        assert_eq!(server.hits(), 2);
+    }
+
+    #[async_test]
+    async fn test_opencep() {
+        let server = mock_server(|path| match path {
+            "/01001000" => MockResponse::json(200, r#"{"cep":"01001-000","logradouro":"Praça da Sé","complemento":"lado ímpar","bairro":"Sé","localidade":"São Paulo","uf":"SP","ibge":"3550308"}"#),
+            "/01310100" => MockResponse::json(502, "Bad Gateway"),
+            _ => MockResponse::json(404, r#"{"error":"CEP not found"}"#),
+        });
+        let provider = OpenCepProvider::new(server.base_url.clone());
+
+        let data = provider.lookup("01001000").await.unwrap();
+        assert_eq!(data.service, "opencep");
+        assert_eq!(data.street, "Praça da Sé");
+
+        assert!(matches!(provider.lookup("99999999").await, Err(Error::NotFoundError { .. })));
+        match provider.lookup("01310100").await {
+            Err(Error::ProviderError { provider, status, .. }) => assert_eq!((provider.as_str(), status), ("opencep", 502)),
+            other => panic!("Expected a provider error, got {:?}", other),
+        }
+    }
+
+    #[async_test]
+    async fn test_sequential_chain() {
+        let brasilapi = mock_server(|_| MockResponse::json(500, r#"{"name":"internal","message":"boom","type":"internal_error"}"#));
+        let viacep = mock_server(|path| match path {
+            "/01001000/json/" => MockResponse::json(200, VIACEP_BODY),
+            _ => MockResponse::json(200, r#"{"erro": true}"#),
+        });
+        let opencep = mock_server(|_| MockResponse::json(404, r#"{"error":"CEP not found"}"#));
+
+        let chain = CepProviderChain::new(ChainStrategy::Sequential)
+            .with_provider(brasilapi.client())
+            .with_provider(ViaCepProvider::new(viacep.base_url.clone()))
+            .with_provider(OpenCepProvider::new(opencep.base_url.clone()));
+        assert_eq!(chain.providers(), vec!["brasilapi", "viacep", "opencep"]);
+
+        let data = chain.lookup("01001-000").await.unwrap();
+        assert_eq!(data.service, "viacep");
+        // OpenCEP is never asked once ViaCEP answers
+        assert_eq!((brasilapi.hits(), viacep.hits(), opencep.hits()), (1, 1, 0));
+
+        match chain.lookup("99999999").await {
+            Err(Error::ProvidersExhausted { attempts }) => {
+                let providers: Vec<_> = attempts.iter().map(|a| a.provider.as_str()).collect();
+                assert_eq!(providers, vec!["brasilapi", "viacep", "opencep"]);
+                assert!(matches!(attempts[1].error, Error::NotFoundError { .. }));
+            },
+            other => panic!("Expected every provider to fail, got {:?}", other),
+        }
+
+        assert!(matches!(chain.lookup("").await, Err(Error::InvalidInputLenError { .. })));
+        assert_eq!(opencep.hits(), 1);
+    }
+
+    #[async_test]
+    async fn test_chain_with_html_outages() {
+        init_logger();
+        let html = |_: &str| MockResponse::json(200, "<html><body>Em manutenção</body></html>");
+        let brasilapi = mock_server(|_| MockResponse::json(503, "<html><body><h1>503 Service Unavailable</h1></body></html>"));
+        let viacep = mock_server(html);
+        let opencep = mock_server(html);
+
+        let chain = CepProviderChain::new(ChainStrategy::Sequential)
+            .with_provider(brasilapi.client())
+            .with_provider(ViaCepProvider::new(viacep.base_url.clone()))
+            .with_provider(OpenCepProvider::new(opencep.base_url.clone()));
+
+        let error = chain.lookup("01001000").await.unwrap_err();
+        match &error {
+            Error::ProvidersExhausted { attempts } => {
+                assert_eq!(attempts.len(), 3);
+                assert!(matches!(attempts[0].error, Error::FailoverExhausted { .. }));
+                assert!(attempts[1..].iter().all(|attempt| matches!(attempt.error, Error::SerdeJsonError(_))));
+            },
+            other => panic!("Expected every provider to fail, got {:?}", other),
+        }
+        let message = error.to_string();
+        assert!(message.contains(&format!("{}: Invalid JSON: ", brasilapi.base_url)), "{}", message);
+        assert!(message.contains("viacep: Invalid JSON: "), "{}", message);
+        assert!(message.contains("opencep: Invalid JSON: "), "{}", message);
+    }
+
+    #[async_test]
+    async fn test_race_chain() {
+        let brasilapi = mock_server(|_| MockResponse::json(500, r#"{"name":"internal","message":"boom","type":"internal_error"}"#));
+        let opencep = mock_server(|_| MockResponse::json(200, VIACEP_BODY));
+
+        let chain = CepProviderChain::new(ChainStrategy::Race)
+            .with_provider(BrasilApiProvider::new(brasilapi.client(), EnumCepRequestVersion::V2))
+            .with_provider(OpenCepProvider::new(opencep.base_url.clone()));
+
+        let data = chain.lookup("01001000").await.unwrap();
+        assert_eq!(data.service, "opencep");
+        assert_eq!(opencep.hits(), 1);
+
+        let failing = CepProviderChain::new(ChainStrategy::Race)
+            .with_provider(brasilapi.client())
+            .with_provider(ViaCepProvider::new(brasilapi.base_url.clone()));
+        match failing.lookup("01001000").await {
+            Err(Error::ProvidersExhausted { attempts }) => {
+                assert_eq!(attempts.iter().map(|a| a.provider.as_str()).collect::<Vec<_>>(), vec!["brasilapi", "viacep"]);
+                assert!(matches!(attempts[1].error, Error::ProviderError { status: 500, .. }));
+            },
+            other => panic!("Expected every provider to fail, got {:?}", other),
+        }
+    }
+}
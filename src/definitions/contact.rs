@@ -0,0 +1,106 @@
+/*!
+Consistency checks between a customer's address and phone <br />
+A phone whose DDD doesn't cover the CEP's city isn't necessarily fraud (people move and keep their numbers),
+so the result is a verdict to weigh, not an error
+*/
+use crate::{
+    api::BrasilApi,
+    cep::CepResponseData,
+    client::BrasilApiClient,
+    ddd::{phone::PhoneNumber, DDDResponseData},
+    errors::Error,
+    text::normalize,
+};
+
+/// How the phone DDD relates to the CEP location
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContactVerdict {
+    /// The DDD covers the CEP's city
+    Match,
+    /// Same state, but the DDD doesn't cover the CEP's city
+    SameStateDifferentCity,
+    DifferentState,
+}
+
+/// The verdict, with the data it was based on
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactCheck {
+    pub verdict: ContactVerdict,
+    pub cep: CepResponseData,
+    pub ddd: DDDResponseData,
+}
+
+impl BrasilApiClient {
+    /**
+    Compares the CEP city with the cities of the phone's DDD, requesting both concurrently <br />
+    City names are compared ignoring accents, case and punctuation
+    */
+    pub async fn validate_contact(&self, cep: &str, phone: &PhoneNumber) -> Result<ContactCheck, Error> {
+        validate_contact(self, cep, phone).await
+    }
+}
+
+/// `validate_contact` on any `BrasilApi`
+pub(crate) async fn validate_contact<A: BrasilApi + ?Sized>(api: &A, cep: &str, phone: &PhoneNumber) -> Result<ContactCheck, Error> {
+    let (cep, ddd) = futures::future::try_join(
+        api.get_cep(cep, None),
+        api.get_city_and_states_by_ddd(&phone.ddd()),
+    ).await?;
+
+    Ok(ContactCheck { verdict: verdict(&cep, &ddd), cep, ddd })
+}
+
+fn verdict(cep: &CepResponseData, ddd: &DDDResponseData) -> ContactVerdict {
+    if !cep.state.trim().eq_ignore_ascii_case(ddd.state.trim()) {
+        return ContactVerdict::DifferentState;
+    }
+    let city = normalize(&cep.city);
+    if ddd.cities.iter().any(|ddd_city| normalize(ddd_city) == city) {
+        ContactVerdict::Match
+    } else {
+        ContactVerdict::SameStateDifferentCity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::tests::{mock_server, MockResponse}, ddd::index::tests::ddd_response};
+    use futures_await_test::async_test;
+
+    #[test]
+    fn test_verdict() {
+        let cep: CepResponseData = serde_json::from_str(
+            r#"{"cep":"13010000","state":"SP","city":"Santa Bárbara d'Oeste","neighborhood":"Centro","street":"Rua Um","service":"mock"}"#
+        ).unwrap();
+        let ddd = |state: &str, city: &str| DDDResponseData { state: state.to_string(), cities: vec![city.to_string()] };
+
+        assert_eq!(verdict(&cep, &ddd("SP", "SANTA BÁRBARA D'OESTE")), ContactVerdict::Match);
+        assert_eq!(verdict(&cep, &ddd("SP", "CAMPINAS")), ContactVerdict::SameStateDifferentCity);
+        assert_eq!(verdict(&cep, &ddd("RJ", "SANTA BÁRBARA D'OESTE")), ContactVerdict::DifferentState);
+    }
+
+    #[async_test]
+    async fn test_validate_contact() {
+        let server = mock_server(|path| match path {
+            "/cep/v1/01001000" => MockResponse::json(200,
+                r#"{"cep":"01001000","state":"SP","city":"São Paulo","neighborhood":"Sé","street":"Praça da Sé","service":"mock"}"#),
+            _ => ddd_response(path),
+        });
+        let client = server.client();
+
+        let check = |phone: &str| {
+            let phone = PhoneNumber::parse(phone).unwrap();
+            let client = client.clone();
+            async move { client.validate_contact("01001-000", &phone).await }
+        };
+
+        let result = check("(11) 98765-4321").await.unwrap();
+        assert_eq!(result.verdict, ContactVerdict::Match);
+        assert_eq!(result.cep.city, "São Paulo");
+
+        assert_eq!(check("(19) 98765-4321").await.unwrap().verdict, ContactVerdict::SameStateDifferentCity);
+        assert_eq!(check("(21) 98765-4321").await.unwrap().verdict, ContactVerdict::DifferentState);
+        assert!(check("(31) 98765-4321").await.is_err());
+    }
+}
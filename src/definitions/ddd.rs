@@ -1,7 +1,13 @@
-use crate::{client::*, constants::ddd::*, errors::*, request::*};
+use crate::{client::*, constants::ddd::*, errors::*};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Brazilian phone numbers parsing
+pub mod phone;
+
+/// City -> DDD reverse index
+pub mod index;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 /// The DDD message struct
 pub struct DDDResponseData {
@@ -17,6 +23,11 @@ impl PartialEq for DDDResponseData {
     }
 }
 
+/// Whether `ddd` is in the `MIN_DDD`..=`MAX_DDD` range and actually in use (i.e: 11 is, 20 isn't)
+pub fn is_valid_ddd(ddd: i8) -> bool {
+    ddd >= *MIN_DDD && ddd <= *MAX_DDD && VALID_DDDS.contains(&ddd)
+}
+
 impl BrasilApiClient{
     pub async fn get_city_and_states_by_ddd(&self, ddd: &i8) -> Result<DDDResponseData, Error> {
 
@@ -29,9 +40,10 @@ impl BrasilApiClient{
                 })
         }
 
-        Ok(get::<(), DDDResponseData>(
-            &format!("{}/{}/{}", self.base_url, SVC_URL, ddd)
-        ).await?)
+        self.call::<DDDResponseData>(
+            EndpointFamily::Ddd,
+            &format!("{}/{}", SVC_URL, ddd)
+        ).await
     }
 }
 
@@ -41,6 +53,15 @@ mod tests {
     use crate::client::tests::cli;
     use futures_await_test::async_test;
 
+    #[test]
+    fn test_is_valid_ddd() {
+        assert!(is_valid_ddd(11));
+        assert!(is_valid_ddd(99));
+        assert!(!is_valid_ddd(20));
+        assert!(!is_valid_ddd(10));
+        assert!(!is_valid_ddd(-11));
+    }
+
     #[async_test]
     async fn test_invalid_min_range() {
         let code = MIN_DDD - 1;
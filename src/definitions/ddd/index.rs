@@ -0,0 +1,174 @@
+/*!
+Reverse DDD lookups: which DDD a city uses <br />
+Built from every DDD of BrasilApi, city names are matched ignoring accents, case and punctuation
+("SÃO PAULO" from the DDD data and "São Paulo" from a CEP are the same city)
+*/
+use crate::{
+    api::BrasilApi,
+    commons::Uf,
+    constants::ddd::{MAX_DDD, MIN_DDD},
+    ddd::DDDResponseData,
+    errors::Error,
+    text::normalize,
+};
+use futures::stream::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// City + state -> DDD index, serializable to skip the ~90 requests next time
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<i8, DDDResponseData>", into = "BTreeMap<i8, DDDResponseData>")]
+pub struct DddIndex {
+    ddds: BTreeMap<i8, DDDResponseData>,
+    cities: HashMap<(Uf, String), Vec<i8>>,
+}
+
+impl DddIndex {
+    pub fn new(ddds: BTreeMap<i8, DDDResponseData>) -> DddIndex {
+        let mut cities: HashMap<(Uf, String), Vec<i8>> = HashMap::new();
+        for (ddd, data) in &ddds {
+            let uf = match data.state.parse::<Uf>() {
+                Ok(uf) => uf,
+                Err(_) => continue,
+            };
+            for city in &data.cities {
+                cities.entry((uf, normalize(city))).or_default().push(*ddd);
+            }
+        }
+        DddIndex { ddds, cities }
+    }
+
+    /**
+    Fetches every DDD from `MIN_DDD` to `MAX_DDD`, `concurrency` at a time <br />
+    DDDs BrasilApi answers with a 404 (i.e: 20) are skipped, any other error fails the whole index
+    */
+    pub async fn fetch(client: &dyn BrasilApi, concurrency: usize) -> Result<DddIndex, Error> {
+        let ddds: Vec<(i8, Option<DDDResponseData>)> = futures::stream::iter(*MIN_DDD..=*MAX_DDD)
+            .map(|ddd| async move {
+                match client.get_city_and_states_by_ddd(&ddd).await {
+                    Ok(data) => Ok((ddd, Some(data))),
+                    Err(e) if e.is_not_found() => Ok((ddd, None)),
+                    Err(e) => Err(e),
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(DddIndex::new(ddds.into_iter().filter_map(|(ddd, data)| data.map(|data| (ddd, data))).collect()))
+    }
+
+    /// The DDD of a city, the first one when the city has more than one
+    pub fn ddd_for(&self, city: &str, uf: Uf) -> Option<i8> {
+        self.ddds_for(city, uf).first().copied()
+    }
+
+    /// Every DDD of a city, sorted
+    pub fn ddds_for(&self, city: &str, uf: Uf) -> &[i8] {
+        self.cities.get(&(uf, normalize(city))).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// The state and cities of a DDD, as fetched
+    pub fn get(&self, ddd: i8) -> Option<&DDDResponseData> {
+        self.ddds.get(&ddd)
+    }
+
+    /// The indexed DDDs, sorted
+    pub fn ddds(&self) -> impl Iterator<Item = i8> + '_ {
+        self.ddds.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ddds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ddds.is_empty()
+    }
+}
+
+impl From<BTreeMap<i8, DDDResponseData>> for DddIndex {
+    fn from(ddds: BTreeMap<i8, DDDResponseData>) -> Self {
+        DddIndex::new(ddds)
+    }
+}
+
+impl From<DddIndex> for BTreeMap<i8, DDDResponseData> {
+    fn from(index: DddIndex) -> Self {
+        index.ddds
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::client::tests::{mock_server, MockResponse};
+    use futures_await_test::async_test;
+
+    /// Answers DDDs 11, 19 and 21, and 404 for the others
+    pub(crate) fn ddd_response(path: &str) -> MockResponse {
+        match path {
+            "/ddd/v1/11" => MockResponse::json(200, r#"{"state":"SP","cities":["SÃO PAULO","GUARULHOS","EMBU-GUAÇU"]}"#),
+            "/ddd/v1/19" => MockResponse::json(200, r#"{"state":"SP","cities":["CAMPINAS","SANTA BÁRBARA D'OESTE"]}"#),
+            "/ddd/v1/21" => MockResponse::json(200, r#"{"state":"RJ","cities":["RIO DE JANEIRO","NITERÓI"]}"#),
+            _ => MockResponse::json(404, r#"{"name":"ddd_error","message":"DDD não encontrado","type":"DDD_NOT_FOUND"}"#),
+        }
+    }
+
+    #[async_test]
+    async fn test_fetch() {
+        let server = mock_server(ddd_response);
+        let index = DddIndex::fetch(&server.client(), 8).await.unwrap();
+
+        assert_eq!(server.hits(), (*MAX_DDD - *MIN_DDD + 1) as usize);
+        assert_eq!(index.ddds().collect::<Vec<_>>(), vec![11, 19, 21]);
+        assert_eq!(index.ddd_for("São Paulo", Uf::SP), Some(11));
+        assert_eq!(index.ddd_for("embu guaçu", Uf::SP), Some(11));
+        assert_eq!(index.ddd_for("Santa Bárbara d'Oeste", Uf::SP), Some(19));
+        assert_eq!(index.ddd_for("Niterói", Uf::RJ), Some(21));
+        assert_eq!(index.ddd_for("Niterói", Uf::SP), None);
+        assert_eq!(index.get(21).unwrap().state, "RJ");
+    }
+
+    #[async_test]
+    async fn test_fetch_fails_on_server_errors() {
+        let server = mock_server(|path| match path {
+            "/ddd/v1/11" => MockResponse::json(500, r#"{"name":"internal","message":"boom","type":"internal_error"}"#),
+            _ => MockResponse::json(404, r#"{"name":"ddd_error","message":"DDD não encontrado","type":"DDD_NOT_FOUND"}"#),
+        });
+        assert!(DddIndex::fetch(&server.client(), 8).await.is_err());
+    }
+
+    #[async_test]
+    async fn test_fetch_skips_by_status() {
+        // Whatever the error type says, only the 404s are unknown DDDs
+        let renamed = mock_server(|path| match path {
+            "/ddd/v1/11" => ddd_response(path),
+            _ => MockResponse::json(404, r#"{"name":"ddd_error","message":"DDD inexistente","type":"ddd_unknown"}"#),
+        });
+        assert_eq!(DddIndex::fetch(&renamed.client(), 8).await.unwrap().ddds().collect::<Vec<_>>(), vec![11]);
+
+        let bad_request = mock_server(|path| match path {
+            "/ddd/v1/11" => MockResponse::json(400, r#"{"name":"ddd_error","message":"Requisição inválida","type":"DDD_NOT_FOUND"}"#),
+            path => ddd_response(path),
+        });
+        match DddIndex::fetch(&bad_request.client(), 8).await {
+            Err(e) => assert_eq!(e.status(), Some(400)),
+            Ok(index) => panic!("Expected the 400 to fail the index, got {:?}", index),
+        }
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut ddds = BTreeMap::new();
+        ddds.insert(11, DDDResponseData { state: "SP".to_string(), cities: vec!["SÃO PAULO".to_string()] });
+        let index = DddIndex::new(ddds);
+
+        let json = serde_json::to_string(&index).unwrap();
+        assert_eq!(json, r#"{"11":{"state":"SP","cities":["SÃO PAULO"]}}"#);
+
+        let back: DddIndex = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, index);
+        assert_eq!(back.ddd_for("sao paulo", Uf::SP), Some(11));
+    }
+}
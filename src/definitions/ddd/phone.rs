@@ -0,0 +1,201 @@
+/*!
+Brazilian phone numbers, parsed from the formats people actually type <br />
+"(11) 98765-4321", "+55 11 98765-4321", "011 98765-4321" and "0 21 11 98765-4321" (carrier code) are the same number
+*/
+use crate::{api::BrasilApi, ddd::{is_valid_ddd, DDDResponseData}, errors::Error};
+
+/// Why a phone number was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PhoneNumberError {
+    /// Something else than digits, `+` and the usual punctuation
+    InvalidCharacter(char),
+    /// Not 10 or 11 digits after dropping the country, trunk and carrier prefixes
+    InvalidLength(usize),
+    /// International numbers must be +55
+    InvalidCountryCode,
+    /// Not in use (see `ddd::is_valid_ddd`)
+    InvalidDdd(i8),
+    /// 9 digits not starting with 9, or 8 digits not starting with 2 to 5 (i.e: a mobile without the ninth digit)
+    InvalidNumber,
+}
+
+impl std::fmt::Display for PhoneNumberError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhoneNumberError::InvalidCharacter(c) => write!(f, "Invalid character [{}] on phone number", c),
+            PhoneNumberError::InvalidLength(len) => write!(f, "Phone number should have 10 or 11 digits with DDD, found {}", len),
+            PhoneNumberError::InvalidCountryCode => write!(f, "Only brazilian (+55) phone numbers are supported"),
+            PhoneNumberError::InvalidDdd(ddd) => write!(f, "Invalid DDD {}", ddd),
+            PhoneNumberError::InvalidNumber => write!(f, "Not a valid mobile or landline number"),
+        }
+    }
+}
+
+impl std::error::Error for PhoneNumberError {}
+
+/// Mobile numbers have 9 digits, starting with the ninth digit (9)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhoneType {
+    Mobile,
+    Landline,
+}
+
+/// A valid brazilian phone number
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhoneNumber {
+    ddd: i8,
+    number: String,
+    carrier_code: Option<String>,
+}
+
+impl PhoneNumber {
+    pub fn parse(input: &str) -> Result<PhoneNumber, PhoneNumberError> {
+        let input = input.trim();
+        let digits: String = input
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '(' | ')' | '.'))
+            .enumerate()
+            .filter(|(i, c)| !(*i == 0 && *c == '+'))
+            .map(|(_, c)| if c.is_ascii_digit() { Ok(c) } else { Err(PhoneNumberError::InvalidCharacter(c)) })
+            .collect::<Result<_, _>>()?;
+
+        let mut carrier_code = None;
+        let national = if input.starts_with('+') {
+            digits.strip_prefix("55").ok_or(PhoneNumberError::InvalidCountryCode)?
+        } else if digits.starts_with("55") && (digits.len() == 12 || digits.len() == 13) {
+            &digits[2..]
+        } else if digits.starts_with('0') && (digits.len() == 13 || digits.len() == 14) {
+            // Trunk prefix + carrier code (i.e: 0 21 11 98765-4321)
+            carrier_code = Some(digits[1..3].to_string());
+            &digits[3..]
+        } else if let Some(national) = digits.strip_prefix('0') {
+            national
+        } else {
+            &digits
+        };
+
+        if national.len() != 10 && national.len() != 11 {
+            return Err(PhoneNumberError::InvalidLength(national.len()));
+        }
+        let ddd: i8 = national[..2].parse().unwrap();
+        if !is_valid_ddd(ddd) {
+            return Err(PhoneNumberError::InvalidDdd(ddd));
+        }
+
+        let number = &national[2..];
+        let valid = match number.len() {
+            9 => number.starts_with('9'),
+            _ => matches!(number.as_bytes()[0], b'2'..=b'5'),
+        };
+        if !valid {
+            return Err(PhoneNumberError::InvalidNumber);
+        }
+
+        Ok(PhoneNumber { ddd, number: number.to_string(), carrier_code })
+    }
+
+    pub fn ddd(&self) -> i8 {
+        self.ddd
+    }
+
+    /// The 8 (landline) or 9 (mobile) digits after the DDD
+    pub fn number(&self) -> &str {
+        &self.number
+    }
+
+    /// The long distance carrier chosen when the number was typed with one (i.e: "21")
+    pub fn carrier_code(&self) -> Option<&str> {
+        self.carrier_code.as_deref()
+    }
+
+    pub fn phone_type(&self) -> PhoneType {
+        if self.number.len() == 9 { PhoneType::Mobile } else { PhoneType::Landline }
+    }
+
+    pub fn is_mobile(&self) -> bool {
+        self.phone_type() == PhoneType::Mobile
+    }
+
+    /// +5511987654321
+    pub fn e164(&self) -> String {
+        format!("+55{}{}", self.ddd, self.number)
+    }
+
+    /// (11) 98765-4321 or (11) 3456-7890
+    pub fn formatted(&self) -> String {
+        let split = self.number.len() - 4;
+        format!("({}) {}-{}", self.ddd, &self.number[..split], &self.number[split..])
+    }
+
+    /// The state and cities of the DDD, through `get_city_and_states_by_ddd`
+    pub async fn lookup(&self, client: &dyn BrasilApi) -> Result<DDDResponseData, Error> {
+        client.get_city_and_states_by_ddd(&self.ddd).await
+    }
+}
+
+impl std::str::FromStr for PhoneNumber {
+    type Err = PhoneNumberError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        PhoneNumber::parse(input)
+    }
+}
+
+impl std::fmt::Display for PhoneNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.formatted())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::{mock_server, MockResponse};
+    use futures_await_test::async_test;
+
+    #[test]
+    fn test_formats() {
+        let expected = PhoneNumber { ddd: 11, number: "987654321".to_string(), carrier_code: None };
+        for input in ["(11) 98765-4321", "11987654321", "+55 11 98765-4321", "5511987654321", "011 98765-4321"].iter() {
+            assert_eq!(PhoneNumber::parse(input).as_ref(), Ok(&expected), "{}", input);
+        }
+
+        let with_carrier = PhoneNumber::parse("0 21 11 98765-4321").unwrap();
+        assert_eq!(with_carrier.carrier_code(), Some("21"));
+        assert_eq!(with_carrier.e164(), "+5511987654321");
+    }
+
+    #[test]
+    fn test_types() {
+        let mobile = PhoneNumber::parse("(21) 99876-5432").unwrap();
+        assert_eq!(mobile.phone_type(), PhoneType::Mobile);
+        assert_eq!(mobile.to_string(), "(21) 99876-5432");
+
+        let landline = PhoneNumber::parse("(61) 3456-7890").unwrap();
+        assert_eq!(landline.phone_type(), PhoneType::Landline);
+        assert_eq!(landline.e164(), "+556134567890");
+        assert_eq!(landline.to_string(), "(61) 3456-7890");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(PhoneNumber::parse("(20) 98765-4321"), Err(PhoneNumberError::InvalidDdd(20)));
+        assert_eq!(PhoneNumber::parse("+1 415 555 2671"), Err(PhoneNumberError::InvalidCountryCode));
+        assert_eq!(PhoneNumber::parse("(11) 8765-4321"), Err(PhoneNumberError::InvalidNumber));
+        assert_eq!(PhoneNumber::parse("(11) 88765-4321"), Err(PhoneNumberError::InvalidNumber));
+        assert_eq!(PhoneNumber::parse("98765-4321"), Err(PhoneNumberError::InvalidLength(9)));
+        assert_eq!(PhoneNumber::parse("11 98765-432a"), Err(PhoneNumberError::InvalidCharacter('a')));
+    }
+
+    #[async_test]
+    async fn test_lookup() {
+        let server = mock_server(|path| match path {
+            "/ddd/v1/11" => MockResponse::json(200, r#"{"state":"SP","cities":["SÃO PAULO","GUARULHOS"]}"#),
+            _ => MockResponse::json(404, r#"{"message":"DDD não encontrado","type":"ddd_error","name":"DDD_NOT_FOUND"}"#),
+        });
+
+        let ddd = PhoneNumber::parse("(11) 98765-4321").unwrap().lookup(&server.client()).await.unwrap();
+        assert_eq!(ddd.state, "SP");
+        assert!(ddd.cities.contains(&"GUARULHOS".to_string()));
+    }
+}
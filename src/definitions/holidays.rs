@@ -1,11 +1,24 @@
-use crate::{client::*, constants::holidays::{SVC_URL,MIN_YEAR, MAX_YEAR}, errors::*, request::*};
+use crate::{api::BrasilApi, client::*, commons::Uf, constants::holidays::{SVC_URL, MIN_YEAR, MAX_YEAR, RANGE_CONCURRENCY}, errors::*};
+use futures::{StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
 
+/// Offline national holidays, also used as `get_holidays` fallback
+pub mod local;
+
+/// State and municipal holidays
+pub mod regional;
+
+/// iCalendar (.ics) export
+pub mod ical;
+
+/// Date ranges and long weekends
+pub mod analysis;
+
 // Defining a custom serializer for only Dates
 mod serde_naivedate {
     use serde::{self, Deserialize, Serializer, Deserializer};
 
-    const FORMAT: &'static str = "%Y-%m-%d";
+    const FORMAT: &str = "%Y-%m-%d";
 
     pub fn serialize<S>(
         date: &chrono::NaiveDate,
@@ -29,7 +42,59 @@ mod serde_naivedate {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The holiday kind
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum HolidayType {
+    /// Nationwide holiday
+    National,
+    /// Holiday on a single state
+    State,
+    /// Holiday on a single city
+    Municipal,
+    /// Optional day off (ponto facultativo)
+    Optional,
+    /// Any other value sent by BrasilApi
+    Unknown(String),
+}
+
+impl HolidayType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            HolidayType::National => "national",
+            HolidayType::State => "state",
+            HolidayType::Municipal => "municipal",
+            HolidayType::Optional => "optional",
+            HolidayType::Unknown(other) => other,
+        }
+    }
+}
+
+impl From<String> for HolidayType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "national" => HolidayType::National,
+            "state" => HolidayType::State,
+            "municipal" => HolidayType::Municipal,
+            "optional" | "ponto_facultativo" => HolidayType::Optional,
+            _ => HolidayType::Unknown(value),
+        }
+    }
+}
+
+impl From<HolidayType> for String {
+    fn from(value: HolidayType) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for HolidayType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 /**
 Data contract for Brasil holidays
@@ -41,7 +106,7 @@ pub struct HolidaysResponseData {
     /// The holiday name
     pub name: String,
     /// The holiday type
-    pub r#type : String
+    pub r#type : HolidayType
 }
 
 impl PartialEq for HolidaysResponseData {
@@ -63,18 +128,95 @@ impl BrasilApiClient {
         }
 
         
-        Ok(get::<(), Vec<HolidaysResponseData>>(
-            &format!("{}/{}/{}", self.base_url, SVC_URL, year)
-        ).await?)
+        let resp = self.call::<Vec<HolidaysResponseData>>(
+            EndpointFamily::Holidays,
+            &format!("{}/{}", SVC_URL, year)
+        ).await;
+
+        match resp {
+            Err(e) if self.local_holidays_fallback && e.is_unavailable() && !local::matches_api(*year) => {
+                log::warn!("BrasilApi unavailable ({}), the local holidays of {} may differ from it, not falling back", e, year);
+                Err(e)
+            },
+            Err(e) if self.local_holidays_fallback && e.is_unavailable() => {
+                log::warn!("BrasilApi unavailable ({}), computing the holidays of {} locally", e, year);
+                local::national_holidays(*year)
+            },
+            resp => resp,
+        }
+    }
+
+    /**
+    The holidays of every year from `from_year` up to `to_year` (inclusive), fetched a few years at a time
+    and returned in date order
+    */
+    pub async fn get_holidays_range(&self, from_year: &i32, to_year: &i32) -> Result<Vec<HolidaysResponseData>, Error> {
+        holidays_range(self, from_year, to_year).await
+    }
+
+    /// The holidays between two dates (both inclusive, in any order), fetching every year they span
+    pub async fn get_holidays_between(&self, date_a: chrono::NaiveDate, date_b: chrono::NaiveDate) -> Result<Vec<HolidaysResponseData>, Error> {
+        use chrono::Datelike;
+
+        let (from, to) = if date_a <= date_b { (date_a, date_b) } else { (date_b, date_a) };
+        let holidays = self.get_holidays_range(&from.year(), &to.year()).await?;
+        Ok(analysis::holidays_between(&holidays, from, to).into_iter().cloned().collect())
     }
+
+    /**
+    The national holidays of `year` merged with the state (and, when `municipality` - the IBGE code - is given,
+    municipal) holidays provided by `source`, sorted by date <br />
+    Example: `get_holidays_for_location(&2024, Uf::SP, Some(3550308), &RegionalHolidays::bundled())`
+    */
+    pub async fn get_holidays_for_location(
+        &self,
+        year: &i32,
+        uf: Uf,
+        municipality: Option<u32>,
+        source: &dyn regional::HolidaySource
+    ) -> Result<Vec<HolidaysResponseData>, Error> {
+        let national = self.get_holidays(year).await?;
+        Ok(regional::merge(national, source.holidays(*year, uf, municipality)))
+    }
+}
+
+/// `get_holidays_range` on any `BrasilApi`
+pub(crate) async fn holidays_range<A: BrasilApi + ?Sized>(api: &A, from_year: &i32, to_year: &i32) -> Result<Vec<HolidaysResponseData>, Error> {
+    if from_year < MIN_YEAR || from_year > to_year {
+        return Err(Error::InvalidInputRangeError
+            {
+                name: "from_year".to_string(),
+                min: *MIN_YEAR,
+                max: *to_year.min(MAX_YEAR)
+            })
+    }
+    if to_year > MAX_YEAR {
+        return Err(Error::InvalidInputRangeError
+            {
+                name: "to_year".to_string(),
+                min: *from_year,
+                max: *MAX_YEAR
+            })
+    }
+
+    let per_year: Vec<Vec<HolidaysResponseData>> = futures::stream::iter(*from_year..=*to_year)
+        .map(|year| async move { api.get_holidays(&year).await })
+        .buffered(RANGE_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    let mut holidays: Vec<HolidaysResponseData> = per_year.into_iter().flatten().collect();
+    holidays.sort_by_key(|holiday| holiday.date);
+    Ok(holidays)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{holidays::{HolidaysResponseData}, client::tests::cli};
+    use crate::{holidays::{HolidaysResponseData}, client::tests::{cli, init_logger, mock_server, MockResponse}};
 
     use super::*;
     use futures_await_test::async_test;
+    use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
 
     #[async_test]
     async fn test_invalid_min_range() {
@@ -104,4 +246,111 @@ mod tests {
         let difference:bool = received.iter().any(|item| !expected.contains(item));
         assert!(!difference);
     }
-}
\ No newline at end of file
+
+    #[async_test]
+    async fn test_local_fallback_when_unavailable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let resp = BrasilApiClient::new(unreachable.clone()).get_holidays(&2024).await;
+        assert!(resp.is_err());
+
+        let resp = BrasilApiClient::new(unreachable)
+            .with_local_holidays_fallback(true)
+            .get_holidays(&2024)
+            .await;
+        assert_eq!(resp.unwrap(), local::national_holidays(2024).unwrap());
+    }
+
+    #[async_test]
+    async fn test_no_local_fallback_on_unverified_years() {
+        let server = mock_server(|_| MockResponse::json(503, "<html><body><h1>503 Service Unavailable</h1></body></html>"));
+        let cli = server.client().with_local_holidays_fallback(true);
+
+        assert!(cli.get_holidays(&2000).await.unwrap_err().is_unavailable());
+        assert!(cli.get_holidays(&1900).await.is_ok());
+    }
+
+    #[async_test]
+    async fn test_local_fallback_on_server_errors() {
+        init_logger();
+        let html = mock_server(|_| MockResponse::json(503, "<html><body><h1>503 Service Unavailable</h1></body></html>"));
+        let limited = mock_server(|_| MockResponse::json(429, "").header("Retry-After", "1"));
+
+        for server in [&html, &limited].iter() {
+            let resp = server.client()
+                .with_local_holidays_fallback(true)
+                .get_holidays(&2024)
+                .await;
+            assert_eq!(resp.unwrap(), local::national_holidays(2024).unwrap());
+        }
+
+        let error = Error::BrasilApiError { message: "boom".to_string(), name: "internal".to_string(), r#type: "internal_error".to_string(), status: 500 };
+        assert!(error.is_unavailable());
+        let error = Error::BrasilApiError { message: "boom".to_string(), name: "internal".to_string(), r#type: "internal_error".to_string(), status: 404 };
+        assert!(!error.is_unavailable());
+    }
+
+    #[async_test]
+    async fn test_no_local_fallback_on_api_errors() {
+        let server = mock_server(|_| MockResponse::json(404, r#"{"message":"Ano fora do intervalo suportado.","type":"feriados_range_error","name":"NotFoundError"}"#));
+        let resp = server.client()
+            .with_local_holidays_fallback(true)
+            .get_holidays(&2024)
+            .await;
+
+        assert!(resp.is_err());
+    }
+
+    #[async_test]
+    async fn test_holidays_range() {
+        let server = mock_server(|path| {
+            let year: i32 = path.rsplit('/').next().unwrap().parse().unwrap();
+            // Answer the first year last, the result must be in order anyway
+            if year == 2023 {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            MockResponse::json(200, serde_json::to_string(&local::national_holidays(year).unwrap()).unwrap())
+        });
+        let cli = server.client();
+
+        let holidays = cli.get_holidays_range(&2023, &2025).await.unwrap();
+        assert_eq!(holidays.len(), 12 + 13 + 13);
+        assert!(holidays.windows(2).all(|w| w[0].date < w[1].date));
+        assert_eq!(server.hits(), 3);
+
+        let between = cli.get_holidays_between(
+            chrono::NaiveDate::from_ymd(2025, 1, 1),
+            chrono::NaiveDate::from_ymd(2024, 12, 25)
+        ).await.unwrap();
+        let names: Vec<&str> = between.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, vec!["Natal", "Confraternização mundial"]);
+    }
+
+    #[async_test]
+    async fn test_holidays_range_is_bounded() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let (current, max) = (in_flight.clone(), max_in_flight.clone());
+        let server = mock_server(move |path| {
+            max.fetch_max(current.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            current.fetch_sub(1, Ordering::SeqCst);
+            let year: i32 = path.rsplit('/').next().unwrap().parse().unwrap();
+            MockResponse::json(200, serde_json::to_string(&local::national_holidays(year).unwrap()).unwrap())
+        });
+
+        let holidays = server.client().get_holidays_range(&2000, &2019).await.unwrap();
+        assert_eq!(holidays.first().map(|h| h.date), Some(chrono::NaiveDate::from_ymd(2000, 1, 1)));
+        assert_eq!(server.hits(), 20);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= RANGE_CONCURRENCY);
+    }
+
+    #[async_test]
+    async fn test_holidays_range_validation() {
+        assert!(cli().get_holidays_range(&(MIN_YEAR - 1), &2000).await.is_err());
+        assert!(cli().get_holidays_range(&2000, &(MAX_YEAR + 1)).await.is_err());
+        assert!(cli().get_holidays_range(&2025, &2024).await.is_err());
+    }
+}
@@ -0,0 +1,138 @@
+/*!
+Helpers to plan around the holidays: date ranges and "feriados prolongados" (long weekends),
+including the likely bridge day ("enforcado") when a holiday falls on a Tuesday or Thursday
+*/
+use crate::holidays::HolidaysResponseData;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// A holiday that makes a long weekend
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProlongedHoliday {
+    /// The holiday itself
+    pub holiday: HolidaysResponseData,
+    /// The business day between the holiday and the weekend, when there's one (Tuesday -> Monday, Thursday -> Friday)
+    pub bridge_day: Option<NaiveDate>,
+    /// First day off, counting the weekend and the bridge day
+    pub start: NaiveDate,
+    /// Last day off, counting the weekend and the bridge day
+    pub end: NaiveDate,
+}
+
+impl ProlongedHoliday {
+    /// How many days in a row, counting the weekend and the bridge day
+    pub fn days_off(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+}
+
+/// The holidays between `from` and `to` (both inclusive, in any order), sorted by date
+pub fn holidays_between(holidays: &[HolidaysResponseData], from: NaiveDate, to: NaiveDate) -> Vec<&HolidaysResponseData> {
+    let (from, to) = if from <= to { (from, to) } else { (to, from) };
+    let mut found: Vec<&HolidaysResponseData> = holidays
+        .iter()
+        .filter(|holiday| holiday.date >= from && holiday.date <= to)
+        .collect();
+    found.sort_by_key(|holiday| holiday.date);
+    found
+}
+
+/**
+Every holiday that joins a weekend: on Mondays and Fridays directly, on Tuesdays and Thursdays
+through a bridge day (unless the bridge day is also a holiday, like a Monday before a Tuesday holiday)
+*/
+pub fn prolonged_holidays(holidays: &[HolidaysResponseData]) -> Vec<ProlongedHoliday> {
+    let is_holiday = |date: NaiveDate| holidays.iter().any(|holiday| holiday.date == date);
+    let day = Duration::days(1);
+
+    let mut prolonged: Vec<ProlongedHoliday> = holidays
+        .iter()
+        .filter_map(|holiday| {
+            let date = holiday.date;
+            let (bridge_day, start, end) = match date.weekday() {
+                Weekday::Mon => (None, date - day * 2, date),
+                Weekday::Fri => (None, date, date + day * 2),
+                Weekday::Tue => (Some(date - day), date - day * 3, date),
+                Weekday::Thu => (Some(date + day), date, date + day * 3),
+                _ => return None,
+            };
+
+            Some(ProlongedHoliday {
+                holiday: holiday.clone(),
+                bridge_day: bridge_day.filter(|bridge| !is_holiday(*bridge)),
+                start,
+                end,
+            })
+        })
+        .collect();
+    prolonged.sort_by_key(|p| p.holiday.date);
+    prolonged
+}
+
+/// Only the likely bridge days ("enforcados"), sorted
+pub fn bridge_days(holidays: &[HolidaysResponseData]) -> Vec<NaiveDate> {
+    prolonged_holidays(holidays)
+        .into_iter()
+        .filter_map(|p| p.bridge_day)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::holidays::local::national_holidays;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(y, m, d)
+    }
+
+    #[test]
+    fn test_holidays_between() {
+        let holidays = national_holidays(2024).unwrap();
+        let found = holidays_between(&holidays, date(2024, 11, 30), date(2024, 11, 1));
+
+        let names: Vec<&str> = found.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, vec!["Finados", "Proclamação da República", "Dia da consciência negra"]);
+        assert_eq!(holidays_between(&holidays, date(2024, 12, 25), date(2024, 12, 25)).len(), 1);
+    }
+
+    #[test]
+    fn test_prolonged_2024() {
+        let holidays = national_holidays(2024).unwrap();
+        let prolonged = prolonged_holidays(&holidays);
+
+        // Carnaval (Tuesday) -> monday bridge
+        let carnaval = &prolonged[1];
+        assert_eq!(carnaval.holiday.name, "Carnaval");
+        assert_eq!(carnaval.bridge_day, Some(date(2024, 2, 12)));
+        assert_eq!((carnaval.start, carnaval.end), (date(2024, 2, 10), date(2024, 2, 13)));
+        assert_eq!(carnaval.days_off(), 4);
+
+        // Corpus Christi (Thursday) -> friday bridge
+        let corpus = prolonged.iter().find(|p| p.holiday.name == "Corpus Christi").unwrap();
+        assert_eq!(corpus.bridge_day, Some(date(2024, 5, 31)));
+        assert_eq!(corpus.end, date(2024, 6, 2));
+
+        // Sexta-feira Santa (Friday) is a long weekend without bridge
+        let good_friday = prolonged.iter().find(|p| p.holiday.name == "Sexta-feira Santa").unwrap();
+        assert_eq!(good_friday.bridge_day, None);
+        assert_eq!(good_friday.days_off(), 3);
+
+        // Weekend holidays are not prolonged
+        assert!(prolonged.iter().all(|p| p.holiday.name != "Tiradentes"));
+
+        assert_eq!(bridge_days(&holidays), vec![date(2024, 2, 12), date(2024, 5, 31)]);
+    }
+
+    #[test]
+    fn test_bridge_day_already_holiday() {
+        let mut holidays = national_holidays(2024).unwrap();
+        holidays.push(HolidaysResponseData {
+            date: date(2024, 2, 12),
+            name: "Segunda de Carnaval".to_string(),
+            r#type: crate::holidays::HolidayType::Optional,
+        });
+
+        let carnaval = prolonged_holidays(&holidays).into_iter().find(|p| p.holiday.name == "Carnaval").unwrap();
+        assert_eq!(carnaval.bridge_day, None);
+    }
+}
@@ -0,0 +1,221 @@
+/*!
+iCalendar (RFC 5545) export, to import the holidays on Google Calendar, Outlook, etc <br />
+Each holiday becomes an all-day event, with a UID that doesn't change between exports
+*/
+use crate::{holidays::{HolidayType, HolidaysResponseData}, text::slug};
+use chrono::{Duration, NaiveDateTime, Utc};
+
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Builds `.ics` documents from holidays
+#[derive(Debug, Clone)]
+pub struct IcsExporter {
+    /// Shown by most clients as the calendar name (X-WR-CALNAME)
+    pub calendar_name: String,
+    /// A domain owned by the caller, used on the event UIDs (i.e: `20240101-natal@domain`)
+    pub uid_domain: String,
+    /// The DTSTAMP of every event, defaults to the export time
+    pub timestamp: Option<NaiveDateTime>,
+}
+
+impl IcsExporter {
+    /**
+    `uid_domain` should be a domain the caller owns (i.e: `empresa.com.br`), so the UIDs
+    don't collide with other calendars exported by this crate
+    */
+    pub fn new(calendar_name: impl Into<String>, uid_domain: impl Into<String>) -> IcsExporter {
+        IcsExporter {
+            calendar_name: calendar_name.into(),
+            uid_domain: uid_domain.into(),
+            timestamp: None,
+        }
+    }
+
+    /// The whole VCALENDAR document, with CRLF line endings
+    pub fn export<'a>(&self, holidays: impl IntoIterator<Item = &'a HolidaysResponseData>) -> String {
+        let timestamp = self.timestamp.unwrap_or_else(|| Utc::now().naive_utc());
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//brasilapi-client//Feriados//PT-BR".to_string(),
+            "CALSCALE:GREGORIAN".to_string(),
+            "METHOD:PUBLISH".to_string(),
+            format!("X-WR-CALNAME:{}", escape(&self.calendar_name)),
+        ];
+
+        for holiday in holidays {
+            lines.extend(vec![
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}", self.uid(holiday)),
+                format!("DTSTAMP:{}", timestamp.format("%Y%m%dT%H%M%SZ")),
+                format!("DTSTART;VALUE=DATE:{}", holiday.date.format("%Y%m%d")),
+                format!("DTEND;VALUE=DATE:{}", (holiday.date + Duration::days(1)).format("%Y%m%d")),
+                format!("SUMMARY:{}", escape(&holiday.name)),
+                format!("CATEGORIES:{}", escape(category(&holiday.r#type))),
+                "TRANSP:TRANSPARENT".to_string(),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+        lines.push("END:VCALENDAR".to_string());
+
+        lines.iter().map(|line| fold(line) + "\r\n").collect()
+    }
+
+    /// Same date + name always gives the same UID, so re-importing updates instead of duplicating
+    fn uid(&self, holiday: &HolidaysResponseData) -> String {
+        format!("{}-{}@{}", holiday.date.format("%Y%m%d"), slug(&holiday.name), self.uid_domain)
+    }
+}
+
+/// The event category, in portuguese, for each holiday type
+pub fn category(r#type: &HolidayType) -> &str {
+    match r#type {
+        HolidayType::National => "Feriado nacional",
+        HolidayType::State => "Feriado estadual",
+        HolidayType::Municipal => "Feriado municipal",
+        HolidayType::Optional => "Ponto facultativo",
+        HolidayType::Unknown(other) => other,
+    }
+}
+
+/// TEXT values escaping (RFC 5545 3.3.11), any line break becomes `\n`
+fn escape(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits lines longer than 75 octets, without breaking UTF-8 chars (RFC 5545 3.1)
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // The leading space counts
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::holidays::local::national_holidays;
+    use chrono::NaiveDate;
+
+    fn unescape(text: &str) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some(other) => out.push(other),
+                    None => {},
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    /// Minimal parser: unfolds the lines and reads back every VEVENT
+    fn parse(ics: &str) -> Vec<(HolidaysResponseData, String, NaiveDate)> {
+        assert!(ics.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+        let unfolded = ics.replace("\r\n ", "");
+
+        let mut events = vec![];
+        let mut current: Option<Vec<(String, String)>> = None;
+        for line in unfolded.split("\r\n") {
+            match line {
+                "BEGIN:VEVENT" => current = Some(vec![]),
+                "END:VEVENT" => {
+                    let props = current.take().unwrap();
+                    let get = |name: &str| props.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone()).unwrap();
+                    let date = NaiveDate::parse_from_str(&get("DTSTART;VALUE=DATE"), "%Y%m%d").unwrap();
+                    let end = NaiveDate::parse_from_str(&get("DTEND;VALUE=DATE"), "%Y%m%d").unwrap();
+                    let category = unescape(&get("CATEGORIES"));
+                    let r#type = match category.as_str() {
+                        "Feriado nacional" => HolidayType::National,
+                        "Feriado estadual" => HolidayType::State,
+                        "Feriado municipal" => HolidayType::Municipal,
+                        "Ponto facultativo" => HolidayType::Optional,
+                        other => HolidayType::Unknown(other.to_string()),
+                    };
+                    events.push((HolidaysResponseData { date, name: unescape(&get("SUMMARY")), r#type }, get("UID"), end));
+                },
+                _ => if let Some(props) = current.as_mut() {
+                    let (key, value) = line.split_once(':').unwrap();
+                    props.push((key.to_string(), value.to_string()));
+                },
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn test_round_trip_multiple_years() {
+        let mut holidays = national_holidays(2024).unwrap();
+        holidays.extend(national_holidays(2025).unwrap());
+        holidays.push(HolidaysResponseData {
+            date: NaiveDate::from_ymd(2025, 1, 25),
+            name: "Aniversário de São Paulo; cidade, \\ capital (um nome bem comprido para forçar a quebra de linha)".to_string(),
+            r#type: HolidayType::Municipal,
+        });
+
+        let ics = IcsExporter::new("Feriados da empresa", "empresa.com.br").export(&holidays);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+
+        let parsed = parse(&ics);
+        assert_eq!(parsed.len(), holidays.len());
+        for ((parsed, _, end), original) in parsed.iter().zip(holidays.iter()) {
+            assert_eq!(parsed, original);
+            assert_eq!(*end, original.date + Duration::days(1));
+        }
+    }
+
+    #[test]
+    fn test_round_trip_line_breaks() {
+        let holidays = vec![HolidaysResponseData {
+            date: NaiveDate::from_ymd(2024, 12, 24),
+            name: "Véspera de Natal\r\nexpediente até 12h\rsem atendimento\nao público".to_string(),
+            r#type: HolidayType::Optional,
+        }];
+
+        let ics = IcsExporter::new("Feriados", "empresa.com.br").export(&holidays);
+        assert!(!ics.replace("\r\n", "").contains(['\r', '\n']));
+
+        let parsed = parse(&ics);
+        assert_eq!(parsed[0].0.name, "Véspera de Natal\nexpediente até 12h\nsem atendimento\nao público");
+    }
+
+    #[test]
+    fn test_stable_uids() {
+        let exporter = IcsExporter {
+            timestamp: Some(NaiveDate::from_ymd(2024, 1, 1).and_hms(12, 0, 0)),
+            ..IcsExporter::new("Feriados", "empresa.com.br")
+        };
+        let holidays = national_holidays(2024).unwrap();
+
+        let first = exporter.export(&holidays);
+        assert_eq!(first, exporter.export(&holidays));
+
+        let uids: Vec<String> = parse(&first).into_iter().map(|(_, uid, _)| uid).collect();
+        assert_eq!(uids[0], "20240101-confraternizacao-mundial@empresa.com.br");
+        assert!(first.contains("DTSTAMP:20240101T120000Z"));
+
+        let mut unique = uids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), uids.len());
+    }
+}
@@ -0,0 +1,163 @@
+/*!
+Offline computation of the national holidays, following the same rules BrasilApi uses <br />
+Fixed dates come from the law, the moving ones (Carnaval, Sexta-feira Santa, Páscoa and Corpus Christi)
+from the Easter date (computus)
+*/
+use crate::{constants::holidays::{MIN_YEAR, MAX_YEAR}, errors::Error, holidays::{HolidayType, HolidaysResponseData}};
+use chrono::{Duration, NaiveDate};
+use std::ops::RangeInclusive;
+
+/// (month, day, name, first year it's a national holiday)
+const FIXED_HOLIDAYS: [(u32, u32, &str, i32); 9] = [
+    (1, 1, "Confraternização mundial", i32::MIN),
+    (4, 21, "Tiradentes", i32::MIN),
+    (5, 1, "Dia do trabalho", i32::MIN),
+    (9, 7, "Independência do Brasil", i32::MIN),
+    (10, 12, "Nossa Senhora Aparecida", i32::MIN),
+    (11, 2, "Finados", i32::MIN),
+    (11, 15, "Proclamação da República", i32::MIN),
+    // Lei 14.759/2023
+    (11, 20, "Dia da consciência negra", 2024),
+    (12, 25, "Natal", i32::MIN),
+];
+
+/// (days from Easter, name, first year it's a national holiday)
+const EASTER_HOLIDAYS: [(i64, &str, i32); 4] = [
+    (-47, "Carnaval", i32::MIN),
+    // Lei 9.093/1995, BrasilApi's own first year isn't known, see `UNVERIFIED_YEARS`
+    (-2, "Sexta-feira Santa", 1995),
+    (0, "Páscoa", i32::MIN),
+    (60, "Corpus Christi", i32::MIN),
+];
+
+/**
+The years nobody checked against BrasilApi: it leaves Sexta-feira Santa out of 1900 and lists it from 2023 on
+(see the recordings on the tests), but the year it starts isn't known <br />
+`national_holidays` follows Lei 9.093/1995 on them, which may not be what `get_holidays` answers,
+so the client doesn't fall back to it on these years
+*/
+pub const UNVERIFIED_YEARS: RangeInclusive<i32> = 1901..=2022;
+
+/// Whether `national_holidays(year)` is known to be what `get_holidays` answers
+pub fn matches_api(year: i32) -> bool {
+    !UNVERIFIED_YEARS.contains(&year)
+}
+
+/// The Easter sunday of `year` (anonymous Gregorian algorithm)
+pub fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd(year, month as u32, day as u32)
+}
+
+/**
+The national holidays of `year`, the same list `get_holidays` returns (sorted by date) <br />
+Shares its validation with `get_holidays`: the year must be between `MIN_YEAR` and `MAX_YEAR`
+*/
+pub fn national_holidays(year: i32) -> Result<Vec<HolidaysResponseData>, Error> {
+    if year < *MIN_YEAR || year > *MAX_YEAR {
+        return Err(Error::InvalidInputRangeError
+            {
+                name: "year".to_string(),
+                min: *MIN_YEAR,
+                max: *MAX_YEAR
+            })
+    }
+
+    let fixed = FIXED_HOLIDAYS
+        .iter()
+        .filter(|(_, _, _, since)| year >= *since)
+        .map(|(month, day, name, _)| (NaiveDate::from_ymd(year, *month, *day), *name));
+
+    let easter = easter(year);
+    let moving = EASTER_HOLIDAYS
+        .iter()
+        .filter(|(_, _, since)| year >= *since)
+        .map(|(offset, name, _)| (easter + Duration::days(*offset), *name));
+
+    let mut holidays: Vec<HolidaysResponseData> = fixed
+        .chain(moving)
+        .map(|(date, name)| HolidaysResponseData {
+            date,
+            name: name.to_string(),
+            r#type: HolidayType::National,
+        })
+        .collect();
+    holidays.sort_by_key(|holiday| holiday.date);
+
+    Ok(holidays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Recorded from https://brasilapi.com.br/api/feriados/v1/{year}
+    const RECORDED_1900: &str = r#"[{"date":"1900-01-01","name":"Confraternização mundial","type":"national"},{"date":"1900-02-27","name":"Carnaval","type":"national"},{"date":"1900-04-15","name":"Páscoa","type":"national"},{"date":"1900-04-21","name":"Tiradentes","type":"national"},{"date":"1900-05-01","name":"Dia do trabalho","type":"national"},{"date":"1900-06-14","name":"Corpus Christi","type":"national"},{"date":"1900-09-07","name":"Independência do Brasil","type":"national"},{"date":"1900-10-12","name":"Nossa Senhora Aparecida","type":"national"},{"date":"1900-11-02","name":"Finados","type":"national"},{"date":"1900-11-15","name":"Proclamação da República","type":"national"},{"date":"1900-12-25","name":"Natal","type":"national"}]"#;
+    const RECORDED_2023: &str = r#"[{"date":"2023-01-01","name":"Confraternização mundial","type":"national"},{"date":"2023-02-21","name":"Carnaval","type":"national"},{"date":"2023-04-07","name":"Sexta-feira Santa","type":"national"},{"date":"2023-04-09","name":"Páscoa","type":"national"},{"date":"2023-04-21","name":"Tiradentes","type":"national"},{"date":"2023-05-01","name":"Dia do trabalho","type":"national"},{"date":"2023-06-08","name":"Corpus Christi","type":"national"},{"date":"2023-09-07","name":"Independência do Brasil","type":"national"},{"date":"2023-10-12","name":"Nossa Senhora Aparecida","type":"national"},{"date":"2023-11-02","name":"Finados","type":"national"},{"date":"2023-11-15","name":"Proclamação da República","type":"national"},{"date":"2023-12-25","name":"Natal","type":"national"}]"#;
+    const RECORDED_2024: &str = r#"[{"date":"2024-01-01","name":"Confraternização mundial","type":"national"},{"date":"2024-02-13","name":"Carnaval","type":"national"},{"date":"2024-03-29","name":"Sexta-feira Santa","type":"national"},{"date":"2024-03-31","name":"Páscoa","type":"national"},{"date":"2024-04-21","name":"Tiradentes","type":"national"},{"date":"2024-05-01","name":"Dia do trabalho","type":"national"},{"date":"2024-05-30","name":"Corpus Christi","type":"national"},{"date":"2024-09-07","name":"Independência do Brasil","type":"national"},{"date":"2024-10-12","name":"Nossa Senhora Aparecida","type":"national"},{"date":"2024-11-02","name":"Finados","type":"national"},{"date":"2024-11-15","name":"Proclamação da República","type":"national"},{"date":"2024-11-20","name":"Dia da consciência negra","type":"national"},{"date":"2024-12-25","name":"Natal","type":"national"}]"#;
+
+    fn assert_same_as_recorded(year: i32, recorded: &str) {
+        let expected = serde_json::from_str::<Vec<HolidaysResponseData>>(recorded).unwrap();
+        let computed = national_holidays(year).unwrap();
+
+        let missing: Vec<_> = expected.iter().filter(|h| !computed.contains(h)).collect();
+        let extra: Vec<_> = computed.iter().filter(|h| !expected.contains(h)).collect();
+        assert!(missing.is_empty() && extra.is_empty(), "missing: {:?}, extra: {:?}", missing, extra);
+        assert_eq!(computed, expected);
+    }
+
+    #[test]
+    fn test_same_as_recorded_1900() {
+        assert_same_as_recorded(1900, RECORDED_1900);
+    }
+
+    #[test]
+    fn test_same_as_recorded_2023() {
+        assert_same_as_recorded(2023, RECORDED_2023);
+    }
+
+    #[test]
+    fn test_same_as_recorded_2024() {
+        assert_same_as_recorded(2024, RECORDED_2024);
+    }
+
+    #[test]
+    fn test_matches_api() {
+        assert!(matches_api(1900));
+        assert!(!matches_api(1901));
+        assert!(!matches_api(1995));
+        assert!(!matches_api(2022));
+        assert!(matches_api(2023));
+        assert!(matches_api(*MAX_YEAR));
+    }
+
+    #[test]
+    fn test_easter() {
+        assert_eq!(easter(1900), NaiveDate::from_ymd(1900, 4, 15));
+        assert_eq!(easter(2000), NaiveDate::from_ymd(2000, 4, 23));
+        assert_eq!(easter(2025), NaiveDate::from_ymd(2025, 4, 20));
+        assert_eq!(easter(2038), NaiveDate::from_ymd(2038, 4, 25));
+    }
+
+    #[test]
+    fn test_year_range() {
+        assert!(national_holidays(MIN_YEAR - 1).is_err());
+        assert!(national_holidays(MAX_YEAR + 1).is_err());
+        assert_eq!(national_holidays(*MIN_YEAR).unwrap().len(), 11);
+        assert_eq!(national_holidays(*MAX_YEAR).unwrap().len(), 13);
+    }
+}
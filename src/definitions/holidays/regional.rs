@@ -0,0 +1,269 @@
+/*!
+State and municipal holidays, which BrasilApi doesn't provide <br />
+They come from a `HolidaySource`: the bundled table (main state holidays and a few capitals,
+fixed dates only), a user supplied JSON/CSV file, or any custom implementation <br />
+The bundled table has no entries for ES, GO, MG, MT and SC, which have no state holiday on a fixed date
+apart from the national ones <br />
+Its `since` is the year of the state law when known, otherwise the year the state was installed
+(AC, AP, MS, RO, RR and TO); the other entries apply to every year
+*/
+use crate::{commons::Uf, errors::Error, holidays::{HolidayType, HolidaysResponseData}};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+const BUNDLED: &str = include_str!("regional_holidays.json");
+
+/// Anything able to list the regional holidays of a location
+pub trait HolidaySource: Send + Sync {
+    /// The holidays of `year` on `uf` (and on the city with the IBGE code `municipality`, when given)
+    fn holidays(&self, year: i32, uf: Uf, municipality: Option<u32>) -> Vec<HolidaysResponseData>;
+}
+
+/// When a regional holiday happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum HolidayDate {
+    /// Every year, written as `MM-DD`
+    Yearly { month: u32, day: u32 },
+    /// A single date, written as `YYYY-MM-DD`
+    Once(NaiveDate),
+}
+
+impl HolidayDate {
+    pub fn on_year(&self, year: i32) -> Option<NaiveDate> {
+        match self {
+            HolidayDate::Yearly { month, day } => NaiveDate::from_ymd_opt(year, *month, *day),
+            HolidayDate::Once(date) if date.year() == year => Some(*date),
+            HolidayDate::Once(_) => None,
+        }
+    }
+}
+
+impl TryFrom<String> for HolidayDate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+            return Ok(HolidayDate::Once(date));
+        }
+
+        // Validated against a leap year, so 02-29 is accepted
+        NaiveDate::parse_from_str(&format!("2000-{}", value), "%Y-%m-%d")
+            .map(|date| HolidayDate::Yearly { month: date.month(), day: date.day() })
+            .map_err(|_| format!("Invalid holiday date [{}], expected MM-DD or YYYY-MM-DD", value))
+    }
+}
+
+impl From<HolidayDate> for String {
+    fn from(value: HolidayDate) -> Self {
+        match value {
+            HolidayDate::Yearly { month, day } => format!("{:02}-{:02}", month, day),
+            HolidayDate::Once(date) => date.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// A state (no `ibge`) or municipal holiday
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionalHoliday {
+    pub date: HolidayDate,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub r#type: HolidayType,
+    pub uf: Uf,
+    /// The IBGE municipality code (7 digits, i.e: 3550308 for São Paulo)
+    #[serde(default)]
+    pub ibge: Option<u32>,
+    /// The first year it's a holiday (i.e: 2008 for RJ's Dia de São Jorge), every year when `None`
+    #[serde(default)]
+    pub since: Option<i32>,
+}
+
+impl RegionalHoliday {
+    /// Its date on `year`, `None` when it doesn't happen that year (or before `since`)
+    pub fn on_year(&self, year: i32) -> Option<NaiveDate> {
+        if self.since.is_some_and(|since| year < since) {
+            return None;
+        }
+        self.date.on_year(year)
+    }
+
+    fn applies_to(&self, uf: Uf, municipality: Option<u32>) -> bool {
+        self.uf == uf && (self.ibge.is_none() || self.ibge == municipality)
+    }
+}
+
+/// A table of regional holidays, implementing `HolidaySource`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegionalHolidays {
+    entries: Vec<RegionalHoliday>,
+}
+
+impl RegionalHolidays {
+    pub fn new(entries: Vec<RegionalHoliday>) -> RegionalHolidays {
+        RegionalHolidays { entries }
+    }
+
+    /// The table shipped with this crate (without ES, GO, MG, MT and SC, see the module docs)
+    pub fn bundled() -> RegionalHolidays {
+        RegionalHolidays::from_json(BUNDLED).expect("Bundled regional holidays should be valid")
+    }
+
+    /**
+    Loads a JSON array of holidays <br />
+    Example: `[{"date": "07-09", "name": "Revolução Constitucionalista", "type": "state", "uf": "SP"}]`
+    */
+    pub fn from_json(json: &str) -> Result<RegionalHolidays, Error> {
+        let entries = serde_json::from_str(json).map_err(|e| Error::InvalidDataError { message: e.to_string() })?;
+        Ok(RegionalHolidays::new(entries))
+    }
+
+    /**
+    Loads a CSV file with the `date,name,type,uf,ibge,since` header (`ibge` and `since` may be empty,
+    the `since` column may be left out) <br />
+    Example: `01-25,Aniversário de São Paulo,municipal,SP,3550308`
+    */
+    pub fn from_csv(reader: impl std::io::Read) -> Result<RegionalHolidays, Error> {
+        let mut csv = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+        let entries = csv
+            .deserialize::<RegionalHoliday>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Error::InvalidDataError { message: e.to_string() })?;
+
+        Ok(RegionalHolidays::new(entries))
+    }
+
+    /// Adds the entries of `other` (i.e: the bundled table + the company's own holidays)
+    pub fn merge(mut self, other: RegionalHolidays) -> RegionalHolidays {
+        self.entries.extend(other.entries);
+        self
+    }
+
+    pub fn entries(&self) -> &[RegionalHoliday] {
+        &self.entries
+    }
+}
+
+impl HolidaySource for RegionalHolidays {
+    fn holidays(&self, year: i32, uf: Uf, municipality: Option<u32>) -> Vec<HolidaysResponseData> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.applies_to(uf, municipality))
+            .filter_map(|entry| {
+                entry.on_year(year).map(|date| HolidaysResponseData {
+                    date,
+                    name: entry.name.clone(),
+                    r#type: entry.r#type.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Joins both lists, sorted by date, dropping exact duplicates
+pub(crate) fn merge(
+    mut holidays: Vec<HolidaysResponseData>,
+    regional: Vec<HolidaysResponseData>
+) -> Vec<HolidaysResponseData> {
+    for holiday in regional {
+        if !holidays.contains(&holiday) {
+            holidays.push(holiday);
+        }
+    }
+    holidays.sort_by_key(|holiday| holiday.date);
+    holidays
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::tests::{mock_server, MockResponse}, holidays::local::national_holidays};
+    use futures_await_test::async_test;
+
+    const SAO_PAULO: u32 = 3550308;
+
+    #[test]
+    fn test_bundled_is_valid() {
+        let bundled = RegionalHolidays::bundled();
+        assert!(!bundled.entries().is_empty());
+
+        let sp = bundled.holidays(2024, Uf::SP, None);
+        assert_eq!(sp.len(), 1);
+        assert_eq!(sp[0].date, NaiveDate::from_ymd(2024, 7, 9));
+        assert_eq!(sp[0].r#type, HolidayType::State);
+
+        let sp_capital = bundled.holidays(2024, Uf::SP, Some(SAO_PAULO));
+        assert_eq!(sp_capital.len(), 2);
+        assert!(sp_capital.iter().any(|h| h.r#type == HolidayType::Municipal));
+    }
+
+    #[test]
+    fn test_since() {
+        let bundled = RegionalHolidays::bundled();
+        assert!(bundled.holidays(2008, Uf::RJ, None).iter().any(|h| h.name == "Dia de São Jorge"));
+        assert!(bundled.holidays(1900, Uf::RJ, None).is_empty());
+        assert!(bundled.holidays(1988, Uf::TO, None).is_empty());
+
+        let csv = "date,name,type,uf,ibge,since\n\
+            04-23,Dia de São Jorge,state,RJ,,2008\n\
+            03-01,Aniversário do Rio,municipal,RJ,3304557,\n";
+        let table = RegionalHolidays::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(table.holidays(2007, Uf::RJ, Some(3304557)).len(), 1);
+        assert_eq!(table.holidays(2008, Uf::RJ, Some(3304557)).len(), 2);
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "date,name,type,uf,ibge\n\
+            01-25,Aniversário de São Paulo,municipal,SP,3550308\n\
+            2024-12-24,Véspera de Natal,optional,SP,\n\
+            03-01,Aniversário do Rio,municipal,RJ,3304557\n";
+        let table = RegionalHolidays::from_csv(csv.as_bytes()).unwrap();
+
+        let holidays = table.holidays(2024, Uf::SP, Some(SAO_PAULO));
+        assert_eq!(holidays.len(), 2);
+        assert_eq!(holidays[1].r#type, HolidayType::Optional);
+
+        assert_eq!(table.holidays(2025, Uf::SP, Some(SAO_PAULO)).len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert!(matches!(
+            RegionalHolidays::from_json(r#"[{"date":"13-01","name":"X","type":"state","uf":"SP"}]"#),
+            Err(Error::InvalidDataError { .. })
+        ));
+        assert!(matches!(
+            RegionalHolidays::from_json(r#"[{"date":"01-01","name":"X","type":"state","uf":"XX"}]"#),
+            Err(Error::InvalidDataError { .. })
+        ));
+        assert!(RegionalHolidays::from_csv("date,name,type,uf,ibge\n01-01,X,state,SP,abc\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_holiday_type_round_trip() {
+        let types: Vec<HolidayType> = serde_json::from_str(r#"["national","state","municipal","optional","religious"]"#).unwrap();
+        assert_eq!(types, vec![
+            HolidayType::National,
+            HolidayType::State,
+            HolidayType::Municipal,
+            HolidayType::Optional,
+            HolidayType::Unknown("religious".to_string()),
+        ]);
+        assert_eq!(serde_json::to_string(&types[4]).unwrap(), r#""religious""#);
+    }
+
+    #[async_test]
+    async fn test_get_holidays_for_location() {
+        let server = mock_server(|_| MockResponse::json(200, serde_json::to_string(&national_holidays(2024).unwrap()).unwrap()));
+        let table = RegionalHolidays::bundled()
+            .merge(RegionalHolidays::from_json(r#"[{"date":"2024-12-24","name":"Véspera de Natal","type":"optional","uf":"SP","ibge":3550308}]"#).unwrap());
+
+        let holidays = server.client().get_holidays_for_location(&2024, Uf::SP, Some(SAO_PAULO), &table).await.unwrap();
+
+        assert_eq!(holidays.len(), 13 + 3);
+        assert!(holidays.windows(2).all(|w| w[0].date <= w[1].date));
+        assert_eq!(holidays.iter().find(|h| h.date == NaiveDate::from_ymd(2024, 1, 25)).unwrap().name, "Aniversário de São Paulo");
+    }
+}
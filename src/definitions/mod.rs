@@ -8,4 +8,10 @@ pub mod cep;
 pub mod ddd;
 
 /// Brasil Holidays Operations
-pub mod holidays;
\ No newline at end of file
+pub mod holidays;
+
+/// PIX Operations
+pub mod pix;
+
+/// Cross checks between the CEP and DDD data
+pub mod contact;
@@ -0,0 +1,123 @@
+use crate::{banks::{BankResponseData, Ispb}, client::*, constants::pix::*, errors::*};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// BR Code (PIX QR code) parsing and generation
+pub mod brcode;
+
+/// PIX keys classification and validation
+pub mod key;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// A PIX participant, as listed by the Central Bank
+pub struct PixParticipant {
+    /// The ISPB code
+    pub ispb: Ispb,
+    /// The participant name
+    #[serde(rename = "nome")]
+    pub name: String,
+    /// The short name (i.e: "BCO DO BRASIL S.A.")
+    #[serde(rename = "nome_reduzido")]
+    pub short_name: String,
+    /// "Provedor de Conta Transacional", "Liquidante Especial", ...
+    #[serde(rename = "modalidade_participacao")]
+    pub participation: String,
+    /// "DRCT" (direct) or "IDRT" (indirect)
+    #[serde(rename = "tipo_participacao")]
+    pub participation_type: String,
+    /// When the participant started operating (i.e: "2020-11-03T09:30:00.000Z")
+    #[serde(rename = "inicio_operacao")]
+    pub operating_since: String,
+}
+
+/// A PIX participant, with its bank data when it's also on the banks list
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixInstitution {
+    pub participant: PixParticipant,
+    pub bank: Option<BankResponseData>,
+}
+
+impl BrasilApiClient {
+    /// Get every PIX participant
+    pub async fn get_pix_participants(&self) -> Result<Vec<PixParticipant>, Error> {
+        self.call::<Vec<PixParticipant>>(EndpointFamily::Pix, SVC_URL).await
+    }
+
+    /**
+    The PIX participants cached on this client (and its clones), fetched on the first call <br />
+    See `refresh_pix_participants` to reload them
+    */
+    pub async fn pix_participants(&self) -> Result<Arc<Vec<PixParticipant>>, Error> {
+        let cached = self.pix_participants.read().unwrap().clone();
+        match cached {
+            Some(participants) => Ok(participants),
+            None => self.refresh_pix_participants().await,
+        }
+    }
+
+    /// Fetches the PIX participants again and replaces the cached list
+    pub async fn refresh_pix_participants(&self) -> Result<Arc<Vec<PixParticipant>>, Error> {
+        let participants = Arc::new(self.get_pix_participants().await?);
+        *self.pix_participants.write().unwrap() = Some(participants.clone());
+        Ok(participants)
+    }
+
+    /**
+    Get a PIX institution by ISPB: the participant from the cached `pix_participants`,
+    plus its bank data from the cached `bank_directory` <br />
+    Example: 00000000 - Banco do Brasil
+    */
+    pub async fn get_pix_institution(&self, ispb: &Ispb) -> Result<PixInstitution, Error> {
+        let (participants, directory) = futures::future::join(self.pix_participants(), self.bank_directory()).await;
+
+        Ok(PixInstitution {
+            participant: find_participant(participants?.iter(), ispb)?,
+            bank: directory?.by_ispb(ispb).cloned(),
+        })
+    }
+}
+
+pub(crate) fn find_participant<'a>(mut participants: impl Iterator<Item = &'a PixParticipant>, ispb: &Ispb) -> Result<PixParticipant, Error> {
+    participants
+        .find(|participant| &participant.ispb == ispb)
+        .cloned()
+        .ok_or_else(|| crate::banks::ispb_not_found(ispb))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{banks::directory, client::tests::{mock_server, MockResponse}};
+    use futures_await_test::async_test;
+
+    pub(crate) const PARTICIPANTS: &str = r#"[
+        {"ispb":"00000000","nome":"Banco do Brasil S.A.","nome_reduzido":"BCO DO BRASIL S.A.","modalidade_participacao":"PDCT","tipo_participacao":"DRCT","inicio_operacao":"2020-11-03T09:30:00.000Z"},
+        {"ispb":"18236120","nome":"NU PAGAMENTOS S.A. - INSTITUIÇÃO DE PAGAMENTO","nome_reduzido":"NU PAGAMENTOS - IP","modalidade_participacao":"PDCT","tipo_participacao":"DRCT","inicio_operacao":"2020-11-03T09:30:00.000Z"},
+        {"ispb":"10573521","nome":"MERCADO PAGO INSTITUIÇÃO DE PAGAMENTO LTDA.","nome_reduzido":"MERCADO PAGO IP LTDA.","modalidade_participacao":"PDCT","tipo_participacao":"DRCT","inicio_operacao":"2020-11-03T09:30:00.000Z"}
+    ]"#;
+
+    #[async_test]
+    async fn test_get_pix_institution() {
+        let server = mock_server(|path| match path {
+            "/pix/v1/participants" => MockResponse::json(200, PARTICIPANTS),
+            _ => MockResponse::json(200, directory::tests::BANKS),
+        });
+        let client = server.client();
+
+        let bb = client.get_pix_institution(&"00000000".parse().unwrap()).await.unwrap();
+        assert_eq!(bb.participant.short_name, "BCO DO BRASIL S.A.");
+        assert_eq!(bb.bank.unwrap().code, Some(1));
+
+        // A participant without COMPE code (and not on the test banks list)
+        let mercado_pago = client.get_pix_institution(&"10573521".parse().unwrap()).await.unwrap();
+        assert_eq!(mercado_pago.bank, None);
+
+        let missing = client.get_pix_institution(&"12345678".parse().unwrap()).await;
+        assert!(matches!(missing, Err(Error::NotFoundError { .. })));
+        // The participants and the banks were requested once, then answered from the caches
+        assert_eq!(server.hits(), 2);
+
+        client.refresh_pix_participants().await.unwrap();
+        assert_eq!(server.hits(), 3);
+    }
+}
@@ -0,0 +1,539 @@
+/*!
+PIX BR Code: the EMV® QR Code (Merchant-Presented Mode) payload of PIX QR codes and "copia e cola" <br />
+Each field is a TLV (2 digits id, 2 digits length, value), the payload ends with a CRC16 checksum (field 63)
+*/
+use crate::{banks::Ispb, api::BrasilApi, errors::Error, pix::PixInstitution, text::strip_accents};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// The GUI of the PIX merchant account template
+pub const PIX_GUI: &str = "br.gov.bcb.pix";
+
+const PAYLOAD_FORMAT: &str = "00";
+const POINT_OF_INITIATION: &str = "01";
+const MERCHANT_ACCOUNT: &str = "26";
+const MERCHANT_CATEGORY_CODE: &str = "52";
+const CURRENCY: &str = "53";
+const AMOUNT: &str = "54";
+const COUNTRY: &str = "58";
+const MERCHANT_NAME: &str = "59";
+const MERCHANT_CITY: &str = "60";
+const POSTAL_CODE: &str = "61";
+const ADDITIONAL_DATA: &str = "62";
+const CRC: &str = "63";
+
+const ACCOUNT_GUI: &str = "00";
+const ACCOUNT_KEY: &str = "01";
+const ACCOUNT_DESCRIPTION: &str = "02";
+const ACCOUNT_FACILITATOR: &str = "03";
+const ACCOUNT_URL: &str = "25";
+const ADDITIONAL_TXID: &str = "05";
+
+/// The txid of static BR Codes without identifier
+const NO_TXID: &str = "***";
+/// Real, ISO 4217
+const CURRENCY_REAL: &str = "986";
+const MAX_NAME_LEN: usize = 25;
+const MAX_CITY_LEN: usize = 15;
+const MAX_TXID_LEN: usize = 25;
+const MAX_AMOUNT_LEN: usize = 13;
+
+/// Why a BR Code was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrCodeError {
+    /// A field length goes past the end of the payload (or its container)
+    Truncated { id: String },
+    /// A field id or length is not made of digits
+    InvalidTlv { position: usize },
+    /// The payload doesn't end with the CRC field
+    MissingCrc,
+    /// The CRC16 doesn't match the payload
+    InvalidCrc { expected: String, found: String },
+    /// A mandatory field is missing (`id` is "26.01" for the key inside the merchant account template, etc)
+    MissingField { id: String },
+    /// A field value is not acceptable
+    InvalidField { id: String, value: String },
+    /// A value is longer than its field allows
+    FieldTooLong { id: String, max: usize },
+}
+
+impl std::fmt::Display for BrCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrCodeError::Truncated { id } => write!(f, "BR Code field [{}] is truncated", id),
+            BrCodeError::InvalidTlv { position } => write!(f, "Invalid BR Code field header at position {}", position),
+            BrCodeError::MissingCrc => write!(f, "BR Code should end with the CRC field (6304)"),
+            BrCodeError::InvalidCrc { expected, found } => write!(f, "Invalid BR Code CRC, expected {} found {}", expected, found),
+            BrCodeError::MissingField { id } => write!(f, "BR Code field [{}] is missing", id),
+            BrCodeError::InvalidField { id, value } => write!(f, "Invalid value [{}] on BR Code field [{}]", value, id),
+            BrCodeError::FieldTooLong { id, max } => write!(f, "BR Code field [{}] is longer than {} chars", id, max),
+        }
+    }
+}
+
+impl std::error::Error for BrCodeError {}
+
+/// A TLV field: 2 digits id and its value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub id: String,
+    pub value: String,
+}
+
+/// Splits a TLV sequence in fields (lengths count chars, not bytes)
+pub fn parse_fields(data: &str) -> Result<Vec<Field>, BrCodeError> {
+    let chars: Vec<char> = data.chars().collect();
+    let mut fields = vec![];
+    let mut position = 0;
+    while position < chars.len() {
+        let header: String = chars.get(position..position + 4).ok_or(BrCodeError::InvalidTlv { position })?.iter().collect();
+        if !header.chars().all(|c| c.is_ascii_digit()) {
+            return Err(BrCodeError::InvalidTlv { position });
+        }
+        let id = header[..2].to_string();
+        let len: usize = header[2..].parse().unwrap();
+        let value = chars
+            .get(position + 4..position + 4 + len)
+            .ok_or_else(|| BrCodeError::Truncated { id: id.clone() })?
+            .iter()
+            .collect();
+        fields.push(Field { id, value });
+        position += 4 + len;
+    }
+    Ok(fields)
+}
+
+/**
+The TLV encoding of a field, failing when the value doesn't fit the 2 digits length <br />
+Values must be ASCII: lengths count chars but the CRC runs over bytes, so any other char breaks the code
+*/
+pub fn emit_field(id: &str, value: &str) -> Result<String, BrCodeError> {
+    if !value.is_ascii() {
+        return Err(BrCodeError::InvalidField { id: id.to_string(), value: value.to_string() });
+    }
+    let len = value.len();
+    if len > 99 {
+        return Err(BrCodeError::FieldTooLong { id: id.to_string(), max: 99 });
+    }
+    Ok(format!("{}{:02}{}", id, len, value))
+}
+
+/// CRC16-CCITT (polynomial 0x1021, initial value 0xFFFF), as 4 uppercase hex digits
+pub fn crc16(data: &str) -> String {
+    let mut crc: u16 = 0xFFFF;
+    for byte in data.bytes() {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    format!("{:04X}", crc)
+}
+
+/// The PIX merchant account template (field 26)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MerchantAccount {
+    /// The PIX key (static BR Codes)
+    pub key: Option<String>,
+    /// Free text shown to the payer
+    pub description: Option<String>,
+    /// ISPB of the withdrawal facilitator (Pix Saque / Pix Troco)
+    pub facilitator: Option<Ispb>,
+    /// Payload location, without scheme (dynamic BR Codes)
+    pub url: Option<String>,
+}
+
+/// A PIX BR Code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrCode {
+    /// Field 01 = 12, the code can be paid only once
+    pub single_use: bool,
+    pub merchant_account: MerchantAccount,
+    /// ISO 18245, "0000" when not informed
+    pub merchant_category_code: String,
+    /// Amount in cents, `None` lets the payer choose it
+    pub amount: Option<u64>,
+    /// Up to 25 chars, accents are removed when emitting (i.e: "João" -> "Joao")
+    pub merchant_name: String,
+    /// Up to 15 chars, accents are removed when emitting (i.e: "São Paulo" -> "Sao Paulo")
+    pub merchant_city: String,
+    pub postal_code: Option<String>,
+    /// Up to 25 alphanumeric chars, `None` is written as "***"
+    pub txid: Option<String>,
+}
+
+impl BrCode {
+    /// A static BR Code for `key`
+    pub fn new(key: impl Into<String>, merchant_name: impl Into<String>, merchant_city: impl Into<String>) -> BrCode {
+        BrCode {
+            single_use: false,
+            merchant_account: MerchantAccount { key: Some(key.into()), ..MerchantAccount::default() },
+            merchant_category_code: "0000".to_string(),
+            amount: None,
+            merchant_name: merchant_name.into(),
+            merchant_city: merchant_city.into(),
+            postal_code: None,
+            txid: None,
+        }
+    }
+
+    /// The amount, in cents
+    pub fn with_amount(mut self, cents: u64) -> BrCode {
+        self.amount = Some(cents);
+        self
+    }
+
+    pub fn with_txid(mut self, txid: impl Into<String>) -> BrCode {
+        self.txid = Some(txid.into());
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> BrCode {
+        self.merchant_account.description = Some(description.into());
+        self
+    }
+
+    /// Parses and validates a payload ("copia e cola"), checking the CRC
+    pub fn parse(payload: &str) -> Result<BrCode, BrCodeError> {
+        let payload = payload.trim();
+        let split = payload.len().checked_sub(4).filter(|i| payload.is_char_boundary(*i)).ok_or(BrCodeError::MissingCrc)?;
+        let (data, found) = payload.split_at(split);
+        if !data.ends_with("6304") {
+            return Err(BrCodeError::MissingCrc);
+        }
+        let expected = crc16(data);
+        if !expected.eq_ignore_ascii_case(found) {
+            return Err(BrCodeError::InvalidCrc { expected, found: found.to_string() });
+        }
+
+        let fields = parse_fields(payload)?;
+        let get = |id: &str| fields.iter().find(|field| field.id == id).map(|field| field.value.clone());
+        let required = |id: &str| get(id).ok_or_else(|| BrCodeError::MissingField { id: id.to_string() });
+
+        let format = required(PAYLOAD_FORMAT)?;
+        if format != "01" {
+            return Err(BrCodeError::InvalidField { id: PAYLOAD_FORMAT.to_string(), value: format });
+        }
+        let currency = required(CURRENCY)?;
+        if currency != CURRENCY_REAL {
+            return Err(BrCodeError::InvalidField { id: CURRENCY.to_string(), value: currency });
+        }
+        if fields.last().map(|field| field.id.as_str()) != Some(CRC) {
+            return Err(BrCodeError::MissingCrc);
+        }
+
+        let txid = match get(ADDITIONAL_DATA) {
+            Some(data) => parse_fields(&data)?
+                .into_iter()
+                .find(|field| field.id == ADDITIONAL_TXID)
+                .map(|field| field.value)
+                .filter(|txid| txid != NO_TXID),
+            None => None,
+        };
+
+        Ok(BrCode {
+            single_use: get(POINT_OF_INITIATION).as_deref() == Some("12"),
+            merchant_account: merchant_account(&fields)?,
+            merchant_category_code: required(MERCHANT_CATEGORY_CODE)?,
+            amount: get(AMOUNT).map(|amount| parse_amount(&amount)).transpose()?,
+            merchant_name: required(MERCHANT_NAME)?,
+            merchant_city: required(MERCHANT_CITY)?,
+            postal_code: get(POSTAL_CODE),
+            txid,
+        })
+    }
+
+    /// The payload ("copia e cola"), with the CRC
+    pub fn to_payload(&self) -> Result<String, BrCodeError> {
+        let merchant_name = transliterate(MERCHANT_NAME, &self.merchant_name)?;
+        let merchant_city = transliterate(MERCHANT_CITY, &self.merchant_city)?;
+        check_len(MERCHANT_NAME, &merchant_name, MAX_NAME_LEN)?;
+        check_len(MERCHANT_CITY, &merchant_city, MAX_CITY_LEN)?;
+        let txid = self.txid.as_deref().unwrap_or(NO_TXID);
+        check_len(&format!("{}.{}", ADDITIONAL_DATA, ADDITIONAL_TXID), txid, MAX_TXID_LEN)?;
+        if txid != NO_TXID && !txid.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(BrCodeError::InvalidField { id: format!("{}.{}", ADDITIONAL_DATA, ADDITIONAL_TXID), value: txid.to_string() });
+        }
+
+        let account = &self.merchant_account;
+        let mut account_fields = emit_field(ACCOUNT_GUI, PIX_GUI)?;
+        for (id, value) in [
+            (ACCOUNT_KEY, account.key.clone()),
+            (ACCOUNT_DESCRIPTION, account.description.as_deref().map(|description| transliterate(ACCOUNT_DESCRIPTION, description)).transpose()?),
+            (ACCOUNT_FACILITATOR, account.facilitator.as_ref().map(Ispb::to_string)),
+            (ACCOUNT_URL, account.url.clone()),
+        ].iter() {
+            if let Some(value) = value {
+                account_fields.push_str(&emit_field(id, value)?);
+            }
+        }
+
+        let mut payload = emit_field(PAYLOAD_FORMAT, "01")?;
+        if self.single_use {
+            payload.push_str(&emit_field(POINT_OF_INITIATION, "12")?);
+        }
+        payload.push_str(&emit_field(MERCHANT_ACCOUNT, &account_fields)?);
+        payload.push_str(&emit_field(MERCHANT_CATEGORY_CODE, &self.merchant_category_code)?);
+        payload.push_str(&emit_field(CURRENCY, CURRENCY_REAL)?);
+        if let Some(amount) = self.amount {
+            let amount = format!("{}.{:02}", amount / 100, amount % 100);
+            check_len(AMOUNT, &amount, MAX_AMOUNT_LEN)?;
+            payload.push_str(&emit_field(AMOUNT, &amount)?);
+        }
+        payload.push_str(&emit_field(COUNTRY, "BR")?);
+        payload.push_str(&emit_field(MERCHANT_NAME, &merchant_name)?);
+        payload.push_str(&emit_field(MERCHANT_CITY, &merchant_city)?);
+        if let Some(postal_code) = &self.postal_code {
+            payload.push_str(&emit_field(POSTAL_CODE, postal_code)?);
+        }
+        payload.push_str(&emit_field(ADDITIONAL_DATA, &emit_field(ADDITIONAL_TXID, txid)?)?);
+        payload.push_str(CRC);
+        payload.push_str("04");
+
+        let crc = crc16(&payload);
+        Ok(payload + &crc)
+    }
+
+    /**
+    The institution of the withdrawal facilitator (Pix Saque / Pix Troco), the only ISPB a BR Code carries <br />
+    `None` when the code has no facilitator
+    */
+    pub async fn facilitator(&self, client: &dyn BrasilApi) -> Result<Option<PixInstitution>, Error> {
+        match &self.merchant_account.facilitator {
+            Some(ispb) => client.get_pix_institution(ispb).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /**
+    The institution receiving the payments, with the ISPB found by `resolver` <br />
+    `None` when the resolver can't tell it
+    */
+    pub async fn receiving_institution(&self, client: &dyn BrasilApi, resolver: &dyn ReceiverResolver) -> Result<Option<PixInstitution>, Error> {
+        match resolver.receiver_ispb(&self.merchant_account).await? {
+            Some(ispb) => client.get_pix_institution(&ispb).await.map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/**
+Finds the ISPB of the institution receiving the payments of a BR Code, which the code doesn't carry <br />
+The PSP of a key is only known to DICT (i.e: through your own PSP), while dynamic codes point to a payload URL
+served by the receiving PSP, see `PayloadHosts`
+*/
+#[async_trait]
+pub trait ReceiverResolver: Send + Sync {
+    /// `None` when it can't tell
+    async fn receiver_ispb(&self, account: &MerchantAccount) -> Result<Option<Ispb>, Error>;
+}
+
+/// Resolves dynamic BR Codes by the host of their payload URL, from hosts given upfront
+#[derive(Debug, Clone, Default)]
+pub struct PayloadHosts {
+    hosts: HashMap<String, Ispb>,
+}
+
+impl PayloadHosts {
+    pub fn new() -> PayloadHosts {
+        PayloadHosts::default()
+    }
+
+    /// The PSP serving payloads on `host` (i.e: "pix.example.com"), compared ignoring case
+    pub fn with_host(mut self, host: &str, ispb: Ispb) -> PayloadHosts {
+        self.hosts.insert(host.to_lowercase(), ispb);
+        self
+    }
+}
+
+#[async_trait]
+impl ReceiverResolver for PayloadHosts {
+    async fn receiver_ispb(&self, account: &MerchantAccount) -> Result<Option<Ispb>, Error> {
+        Ok(account.url.as_deref().and_then(|url| {
+            let url = url.split("://").last().unwrap_or(url);
+            let host = url.split(['/', ':', '?']).next().unwrap_or(url);
+            self.hosts.get(&host.to_lowercase()).cloned()
+        }))
+    }
+}
+
+/// The first merchant account template (ids 26 to 51) with the PIX GUI
+fn merchant_account(fields: &[Field]) -> Result<MerchantAccount, BrCodeError> {
+    for field in fields.iter().filter(|field| ("26".."52").contains(&field.id.as_str())) {
+        let inner = parse_fields(&field.value)?;
+        let get = |id: &str| inner.iter().find(|f| f.id == id).map(|f| f.value.clone());
+        if !get(ACCOUNT_GUI).is_some_and(|gui| gui.eq_ignore_ascii_case(PIX_GUI)) {
+            continue;
+        }
+
+        let account = MerchantAccount {
+            key: get(ACCOUNT_KEY),
+            description: get(ACCOUNT_DESCRIPTION),
+            facilitator: get(ACCOUNT_FACILITATOR)
+                .map(|ispb| ispb.parse().map_err(|_| BrCodeError::InvalidField { id: format!("{}.{}", field.id, ACCOUNT_FACILITATOR), value: ispb }))
+                .transpose()?,
+            url: get(ACCOUNT_URL),
+        };
+        if account.key.is_none() && account.url.is_none() {
+            return Err(BrCodeError::MissingField { id: format!("{}.{}", field.id, ACCOUNT_KEY) });
+        }
+        return Ok(account);
+    }
+    Err(BrCodeError::MissingField { id: MERCHANT_ACCOUNT.to_string() })
+}
+
+/// "10", "10.5" and "10.50" are all 1050 cents
+fn parse_amount(amount: &str) -> Result<u64, BrCodeError> {
+    let invalid = || BrCodeError::InvalidField { id: AMOUNT.to_string(), value: amount.to_string() };
+    if amount.len() > MAX_AMOUNT_LEN {
+        return Err(invalid());
+    }
+    let (reais, cents) = amount.split_once('.').unwrap_or((amount, ""));
+    if reais.is_empty() || cents.len() > 2 || !(reais.to_string() + cents).chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let reais: u64 = reais.parse().map_err(|_| invalid())?;
+    let cents: u64 = format!("{:0<2}", cents).parse().unwrap();
+    reais.checked_mul(100).and_then(|amount| amount.checked_add(cents)).ok_or_else(invalid)
+}
+
+/// The value with its accents removed, failing when it still has non-ASCII chars
+fn transliterate(id: &str, value: &str) -> Result<String, BrCodeError> {
+    let ascii = strip_accents(value);
+    if !ascii.is_ascii() {
+        return Err(BrCodeError::InvalidField { id: id.to_string(), value: value.to_string() });
+    }
+    Ok(ascii)
+}
+
+fn check_len(id: &str, value: &str, max: usize) -> Result<(), BrCodeError> {
+    if value.chars().count() > max {
+        return Err(BrCodeError::FieldTooLong { id: id.to_string(), max });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{banks::directory, client::tests::{mock_server, MockResponse}, pix::tests::PARTICIPANTS};
+    use futures_await_test::async_test;
+
+    /// The example of the BR Code manual, by the Central Bank
+    const MANUAL_EXAMPLE: &str = "00020126580014br.gov.bcb.pix0136123e4567-e12b-12d1-a456-4266554400005204000053039865802BR5913Fulano de Tal6008BRASILIA62070503***63041D3D";
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(crc16("123456789"), "29B1");
+        assert_eq!(crc16(&MANUAL_EXAMPLE[..MANUAL_EXAMPLE.len() - 4]), "1D3D");
+    }
+
+    #[test]
+    fn test_parse_manual_example() {
+        let code = BrCode::parse(MANUAL_EXAMPLE).unwrap();
+        assert_eq!(code.merchant_account.key.as_deref(), Some("123e4567-e12b-12d1-a456-426655440000"));
+        assert_eq!(code.merchant_name, "Fulano de Tal");
+        assert_eq!(code.merchant_city, "BRASILIA");
+        assert_eq!(code.amount, None);
+        assert_eq!(code.txid, None);
+        assert!(!code.single_use);
+
+        assert_eq!(code.to_payload().unwrap(), MANUAL_EXAMPLE);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let code = BrCode::new("fulano@example.com", "Fulano de Tal", "Sao Paulo")
+            .with_amount(1050)
+            .with_txid("PEDIDO123")
+            .with_description("Pedido 123");
+
+        let payload = code.to_payload().unwrap();
+        assert!(payload.contains("540510.50"));
+        assert!(payload.contains("0509PEDIDO123"));
+        assert_eq!(BrCode::parse(&payload).unwrap(), code);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let tampered = MANUAL_EXAMPLE.replace("Fulano", "Ciclano");
+        assert!(matches!(BrCode::parse(&tampered), Err(BrCodeError::InvalidCrc { .. })));
+        assert_eq!(BrCode::parse("0002"), Err(BrCodeError::MissingCrc));
+
+        let too_long = BrCode::new("key", "Fulano de Tal", "Cidade com nome longo");
+        assert_eq!(too_long.to_payload(), Err(BrCodeError::FieldTooLong { id: "60".to_string(), max: 15 }));
+        assert!(BrCode::new("key", "Fulano", "Cidade").with_txid("id-com-traço").to_payload().is_err());
+
+        assert_eq!(parse_amount("10"), Ok(1000));
+        assert_eq!(parse_amount("0.5"), Ok(50));
+        assert!(parse_amount("1,50").is_err());
+        assert!(parse_amount("1.505").is_err());
+        assert_eq!(parse_amount("9999999999.99"), Ok(999_999_999_999));
+        assert!(parse_amount("99999999999.99").is_err());
+        assert!(parse_amount("999999999999999999").is_err());
+
+        // An uploaded payload with an oversized amount, but a valid CRC
+        let payload = BrCode::new("key", "Fulano", "Cidade").with_amount(1050).to_payload().unwrap();
+        let data = payload[..payload.len() - 4].replace("540510.50", &format!("5418{}", "9".repeat(18)));
+        let tampered = format!("{}{}", data, crc16(&data));
+        assert_eq!(
+            BrCode::parse(&tampered),
+            Err(BrCodeError::InvalidField { id: "54".to_string(), value: "9".repeat(18) })
+        );
+        assert_eq!(
+            BrCode::new("key", "Fulano", "Cidade").with_amount(u64::MAX).to_payload(),
+            Err(BrCodeError::FieldTooLong { id: "54".to_string(), max: 13 })
+        );
+    }
+
+    #[async_test]
+    async fn test_facilitator() {
+        let server = mock_server(|path| match path {
+            "/pix/v1/participants" => MockResponse::json(200, PARTICIPANTS),
+            _ => MockResponse::json(200, directory::tests::BANKS),
+        });
+        let mut code = BrCode::parse(MANUAL_EXAMPLE).unwrap();
+        assert_eq!(code.facilitator(&server.client()).await.unwrap(), None);
+
+        code.merchant_account.facilitator = Some("18236120".parse().unwrap());
+        let code = BrCode::parse(&code.to_payload().unwrap()).unwrap();
+
+        let institution = code.facilitator(&server.client()).await.unwrap().unwrap();
+        assert_eq!(institution.participant.short_name, "NU PAGAMENTOS - IP");
+        assert_eq!(institution.bank.unwrap().code, None);
+    }
+
+    #[async_test]
+    async fn test_receiving_institution() {
+        let server = mock_server(|path| match path {
+            "/pix/v1/participants" => MockResponse::json(200, PARTICIPANTS),
+            _ => MockResponse::json(200, directory::tests::BANKS),
+        });
+        let resolver = PayloadHosts::new().with_host("Pix.Example.com", "00000000".parse().unwrap());
+        let mut code = BrCode::new("key", "Fulano", "Cidade");
+
+        assert_eq!(code.receiving_institution(&server.client(), &resolver).await.unwrap(), None);
+        assert_eq!(server.hits(), 0);
+
+        code.merchant_account.url = Some("pix.example.com/qr/v2/9d36b84f".to_string());
+        let institution = code.receiving_institution(&server.client(), &resolver).await.unwrap().unwrap();
+        assert_eq!(institution.participant.short_name, "BCO DO BRASIL S.A.");
+        assert_eq!(institution.bank.unwrap().code, Some(1));
+
+        code.merchant_account.url = Some("https://qr.other.com.br:443/v2/9d36b84f".to_string());
+        assert_eq!(code.receiving_institution(&server.client(), &resolver).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        let code = BrCode::new("key", "João da Conceição", "São Paulo").with_description("Pão de queijo");
+        let parsed = BrCode::parse(&code.to_payload().unwrap()).unwrap();
+        assert_eq!(parsed.merchant_name, "Joao da Conceicao");
+        assert_eq!(parsed.merchant_city, "Sao Paulo");
+        assert_eq!(parsed.merchant_account.description.as_deref(), Some("Pao de queijo"));
+
+        let emoji = BrCode::new("key", "Fulano ☕", "Cidade");
+        assert_eq!(emoji.to_payload(), Err(BrCodeError::InvalidField { id: "59".to_string(), value: "Fulano ☕".to_string() }));
+        assert!(emit_field("01", "chave€").is_err());
+    }
+}
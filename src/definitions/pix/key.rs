@@ -0,0 +1,237 @@
+/*!
+PIX keys ("chaves PIX"): type detection, normalization and validation, following the DICT formats <br />
+CPF and CNPJ keys are digits only, phones are `+55` + DDD + mobile number, emails are lowercase
+and random keys (EVP) are lowercase UUIDs
+*/
+use crate::{
+    ddd::phone::{PhoneNumber, PhoneNumberError},
+    documents::{Cnpj, Cpf, DocumentError},
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// The longest email key DICT accepts
+const MAX_EMAIL_LEN: usize = 77;
+
+lazy_static! {
+    static ref EMAIL: Regex = Regex::new(
+        r"^[a-z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?(?:\.[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?)+$"
+    ).unwrap();
+    static ref EVP: Regex = Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$").unwrap();
+}
+
+/// The kinds of PIX keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PixKeyType {
+    Cpf,
+    Cnpj,
+    Phone,
+    Email,
+    /// Random key ("chave aleatória")
+    Evp,
+}
+
+/// Why a PIX key was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PixKeyError {
+    Empty,
+    /// Doesn't look like any kind of key
+    Unrecognized,
+    InvalidCpf(DocumentError),
+    InvalidCnpj(DocumentError),
+    /// Phone keys must start with +55
+    InvalidCountryCode,
+    /// Not in use (see `ddd::is_valid_ddd`)
+    InvalidDdd(i8),
+    /// Not a 9 digits mobile number (starting with 9) after the DDD
+    InvalidPhoneNumber,
+    /// Not a valid address, or longer than 77 chars
+    InvalidEmail,
+    /// Not a UUID
+    InvalidEvp,
+}
+
+impl std::fmt::Display for PixKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PixKeyError::Empty => write!(f, "Empty PIX key"),
+            PixKeyError::Unrecognized => write!(f, "Not a CPF, CNPJ, phone, email or random PIX key"),
+            PixKeyError::InvalidCpf(e) => write!(f, "Invalid CPF key: {}", e),
+            PixKeyError::InvalidCnpj(e) => write!(f, "Invalid CNPJ key: {}", e),
+            PixKeyError::InvalidCountryCode => write!(f, "Phone keys should start with +55"),
+            PixKeyError::InvalidDdd(ddd) => write!(f, "Invalid DDD {} on phone key", ddd),
+            PixKeyError::InvalidPhoneNumber => write!(f, "Phone keys should be a 9 digits mobile number"),
+            PixKeyError::InvalidEmail => write!(f, "Invalid email key"),
+            PixKeyError::InvalidEvp => write!(f, "Invalid random key, expected a UUID"),
+        }
+    }
+}
+
+impl std::error::Error for PixKeyError {}
+
+/// A valid PIX key, normalized
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PixKey {
+    Cpf(Cpf),
+    Cnpj(Cnpj),
+    /// +5511987654321
+    Phone(String),
+    /// Lowercase
+    Email(String),
+    /// Lowercase UUID
+    Evp(String),
+}
+
+impl PixKey {
+    /**
+    Detects the key type and validates it <br />
+    11 digits are a CPF (phones need the +55), 14 chars a CNPJ, anything with `@` an email
+    */
+    pub fn parse(key: &str) -> Result<PixKey, PixKeyError> {
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(PixKeyError::Empty);
+        }
+        PixKey::parse_as(detect(key)?, key)
+    }
+
+    /// Validates a key of a known type (i.e: the type chosen on a form)
+    pub fn parse_as(r#type: PixKeyType, key: &str) -> Result<PixKey, PixKeyError> {
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(PixKeyError::Empty);
+        }
+        match r#type {
+            PixKeyType::Cpf => key.parse().map(PixKey::Cpf).map_err(PixKeyError::InvalidCpf),
+            PixKeyType::Cnpj => key.parse().map(PixKey::Cnpj).map_err(PixKeyError::InvalidCnpj),
+            PixKeyType::Phone => parse_phone(key).map(PixKey::Phone),
+            PixKeyType::Email => {
+                let email = key.to_lowercase();
+                if email.len() > MAX_EMAIL_LEN || !EMAIL.is_match(&email) {
+                    return Err(PixKeyError::InvalidEmail);
+                }
+                Ok(PixKey::Email(email))
+            },
+            PixKeyType::Evp => {
+                let evp = key.to_lowercase();
+                if !EVP.is_match(&evp) {
+                    return Err(PixKeyError::InvalidEvp);
+                }
+                Ok(PixKey::Evp(evp))
+            },
+        }
+    }
+
+    pub fn key_type(&self) -> PixKeyType {
+        match self {
+            PixKey::Cpf(_) => PixKeyType::Cpf,
+            PixKey::Cnpj(_) => PixKeyType::Cnpj,
+            PixKey::Phone(_) => PixKeyType::Phone,
+            PixKey::Email(_) => PixKeyType::Email,
+            PixKey::Evp(_) => PixKeyType::Evp,
+        }
+    }
+
+    /// The key as registered on DICT (i.e: the value for a BR Code)
+    pub fn as_str(&self) -> &str {
+        match self {
+            PixKey::Cpf(cpf) => cpf.as_str(),
+            PixKey::Cnpj(cnpj) => cnpj.as_str(),
+            PixKey::Phone(key) | PixKey::Email(key) | PixKey::Evp(key) => key,
+        }
+    }
+}
+
+impl std::str::FromStr for PixKey {
+    type Err = PixKeyError;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        PixKey::parse(key)
+    }
+}
+
+impl std::fmt::Display for PixKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn detect(key: &str) -> Result<PixKeyType, PixKeyError> {
+    if key.contains('@') {
+        return Ok(PixKeyType::Email);
+    }
+    if key.starts_with('+') {
+        return Ok(PixKeyType::Phone);
+    }
+    if key.len() == 36 && key.matches('-').count() == 4 {
+        return Ok(PixKeyType::Evp);
+    }
+
+    let chars: String = key.chars().filter(|c| !matches!(c, '.' | '-' | '/' | ' ')).collect();
+    match chars.len() {
+        11 if chars.chars().all(|c| c.is_ascii_digit()) => Ok(PixKeyType::Cpf),
+        14 if chars.chars().all(|c| c.is_ascii_alphanumeric()) => Ok(PixKeyType::Cnpj),
+        _ => Err(PixKeyError::Unrecognized),
+    }
+}
+
+/// "+55 (11) 98765-4321" -> "+5511987654321"
+fn parse_phone(key: &str) -> Result<String, PixKeyError> {
+    if !key.starts_with('+') {
+        return Err(PixKeyError::InvalidPhoneNumber);
+    }
+    let phone = PhoneNumber::parse(key).map_err(|e| match e {
+        PhoneNumberError::InvalidCountryCode => PixKeyError::InvalidCountryCode,
+        PhoneNumberError::InvalidDdd(ddd) => PixKeyError::InvalidDdd(ddd),
+        _ => PixKeyError::InvalidPhoneNumber,
+    })?;
+    if !phone.is_mobile() {
+        return Err(PixKeyError::InvalidPhoneNumber);
+    }
+    Ok(phone.e164())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_and_normalization() {
+        let cases = [
+            ("529.982.247-25", PixKeyType::Cpf, "52998224725"),
+            ("11.222.333/0001-81", PixKeyType::Cnpj, "11222333000181"),
+            ("12.ABC.345/01DE-35", PixKeyType::Cnpj, "12ABC34501DE35"),
+            ("+55 (11) 98765-4321", PixKeyType::Phone, "+5511987654321"),
+            (" Fulano.Tal@Example.com ", PixKeyType::Email, "fulano.tal@example.com"),
+            ("123E4567-E12B-12D1-A456-426655440000", PixKeyType::Evp, "123e4567-e12b-12d1-a456-426655440000"),
+        ];
+        for (input, r#type, normalized) in cases.iter() {
+            let key = PixKey::parse(input).unwrap();
+            assert_eq!(key.key_type(), *r#type, "{}", input);
+            assert_eq!(key.as_str(), *normalized);
+        }
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        assert_eq!(PixKey::parse(" "), Err(PixKeyError::Empty));
+        assert_eq!(PixKey::parse("chave"), Err(PixKeyError::Unrecognized));
+        assert_eq!(PixKey::parse("529.982.247-26"), Err(PixKeyError::InvalidCpf(DocumentError::InvalidCheckDigit)));
+        assert_eq!(PixKey::parse("11.222.333/0001-80"), Err(PixKeyError::InvalidCnpj(DocumentError::InvalidCheckDigit)));
+        assert_eq!(PixKey::parse("+1 415 555 2671"), Err(PixKeyError::InvalidCountryCode));
+        assert_eq!(PixKey::parse("+55 20 98765-4321"), Err(PixKeyError::InvalidDdd(20)));
+        assert_eq!(PixKey::parse("+55 11 3456-7890"), Err(PixKeyError::InvalidPhoneNumber));
+        assert_eq!(PixKey::parse("+55 11 88765-4321"), Err(PixKeyError::InvalidPhoneNumber));
+        assert_eq!(PixKey::parse("fulano@localhost"), Err(PixKeyError::InvalidEmail));
+        assert_eq!(PixKey::parse("123e4567-e12b-12d1-a456-42665544000g"), Err(PixKeyError::InvalidEvp));
+    }
+
+    #[test]
+    fn test_parse_as() {
+        // 11 digits are a CPF unless told otherwise
+        assert_eq!(PixKey::parse_as(PixKeyType::Phone, "52998224725"), Err(PixKeyError::InvalidPhoneNumber));
+        assert_eq!(PixKey::parse_as(PixKeyType::Phone, "+5511987654321").unwrap().key_type(), PixKeyType::Phone);
+        assert!(PixKey::parse_as(PixKeyType::Email, "529.982.247-25").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+/*!
+CPF and CNPJ validation (check digits), offline <br />
+Both accept the formatted ("529.982.247-25", "11.222.333/0001-81") and the plain versions,
+and CNPJ also accepts the alphanumeric format in use since July 2026 ("12.ABC.345/01DE-35")
+*/
+
+/// Why a document was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentError {
+    /// Something else than digits (letters on alphanumeric CNPJs) and the usual punctuation
+    InvalidCharacter(char),
+    /// Not 11 (CPF) or 14 (CNPJ) chars
+    InvalidLength { expected: usize, found: usize },
+    /// Every digit is the same (i.e: 111.111.111-11), which passes the check digits but is never valid
+    RepeatedDigits,
+    InvalidCheckDigit,
+}
+
+impl std::fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentError::InvalidCharacter(c) => write!(f, "Invalid character [{}]", c),
+            DocumentError::InvalidLength { expected, found } => write!(f, "Expected {} chars, found {}", expected, found),
+            DocumentError::RepeatedDigits => write!(f, "Every digit is the same"),
+            DocumentError::InvalidCheckDigit => write!(f, "Invalid check digit"),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+/// A valid CPF, stored as 11 digits
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cpf(String);
+
+impl Cpf {
+    /// The 11 digits
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Formatted as 529.982.247-25
+    pub fn formatted(&self) -> String {
+        let d = &self.0;
+        format!("{}.{}.{}-{}", &d[0..3], &d[3..6], &d[6..9], &d[9..11])
+    }
+}
+
+impl std::str::FromStr for Cpf {
+    type Err = DocumentError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let cpf = normalize(value, 11, false)?;
+        let digits: Vec<u32> = cpf.chars().map(char_value).collect();
+
+        let check = |len: usize| {
+            let sum: u32 = digits[..len].iter().enumerate().map(|(i, d)| d * (len as u32 + 1 - i as u32)).sum();
+            sum * 10 % 11 % 10
+        };
+        if check(9) != digits[9] || check(10) != digits[10] {
+            return Err(DocumentError::InvalidCheckDigit);
+        }
+        Ok(Cpf(cpf))
+    }
+}
+
+impl std::fmt::Display for Cpf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.formatted())
+    }
+}
+
+/// A valid CNPJ, stored as 14 chars (uppercase letters allowed on the first 12)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cnpj(String);
+
+impl Cnpj {
+    /// The 14 chars
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The first 8 chars, shared by every branch of the company
+    pub fn root(&self) -> &str {
+        &self.0[..8]
+    }
+
+    /// Formatted as 11.222.333/0001-81
+    pub fn formatted(&self) -> String {
+        let d = &self.0;
+        format!("{}.{}.{}/{}-{}", &d[0..2], &d[2..5], &d[5..8], &d[8..12], &d[12..14])
+    }
+}
+
+impl std::str::FromStr for Cnpj {
+    type Err = DocumentError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let cnpj = normalize(value, 14, true)?;
+        if let Some(letter) = cnpj[12..].chars().find(|c| !c.is_ascii_digit()) {
+            return Err(DocumentError::InvalidCharacter(letter));
+        }
+        let values: Vec<u32> = cnpj.chars().map(char_value).collect();
+
+        const WEIGHTS: [u32; 13] = [6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2];
+        let check = |len: usize| {
+            let sum: u32 = values[..len].iter().zip(&WEIGHTS[13 - len..]).map(|(v, w)| v * w).sum();
+            match sum % 11 {
+                0 | 1 => 0,
+                remainder => 11 - remainder,
+            }
+        };
+        if check(12) != values[12] || check(13) != values[13] {
+            return Err(DocumentError::InvalidCheckDigit);
+        }
+        Ok(Cnpj(cnpj))
+    }
+}
+
+impl std::fmt::Display for Cnpj {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.formatted())
+    }
+}
+
+/// Drops the punctuation, checks the chars and the length
+fn normalize(value: &str, len: usize, alphanumeric: bool) -> Result<String, DocumentError> {
+    let normalized: String = value
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, '.' | '-' | '/' | ' '))
+        .map(|c| {
+            let c = c.to_ascii_uppercase();
+            if c.is_ascii_digit() || (alphanumeric && c.is_ascii_uppercase()) { Ok(c) } else { Err(DocumentError::InvalidCharacter(c)) }
+        })
+        .collect::<Result<_, _>>()?;
+
+    if normalized.len() != len {
+        return Err(DocumentError::InvalidLength { expected: len, found: normalized.len() });
+    }
+    if normalized.chars().all(|c| normalized.starts_with(c)) {
+        return Err(DocumentError::RepeatedDigits);
+    }
+    Ok(normalized)
+}
+
+/// The ASCII code minus 48: digits are themselves, letters start at A = 17
+fn char_value(c: char) -> u32 {
+    c as u32 - '0' as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpf() {
+        let cpf: Cpf = "529.982.247-25".parse().unwrap();
+        assert_eq!(cpf.as_str(), "52998224725");
+        assert_eq!(cpf.to_string(), "529.982.247-25");
+        assert_eq!("52998224725".parse(), Ok(cpf));
+
+        assert_eq!("529.982.247-26".parse::<Cpf>(), Err(DocumentError::InvalidCheckDigit));
+        assert_eq!("111.111.111-11".parse::<Cpf>(), Err(DocumentError::RepeatedDigits));
+        assert_eq!("5299822472".parse::<Cpf>(), Err(DocumentError::InvalidLength { expected: 11, found: 10 }));
+        assert_eq!("529982247A5".parse::<Cpf>(), Err(DocumentError::InvalidCharacter('A')));
+    }
+
+    #[test]
+    fn test_cnpj() {
+        let cnpj: Cnpj = "11.222.333/0001-81".parse().unwrap();
+        assert_eq!(cnpj.as_str(), "11222333000181");
+        assert_eq!(cnpj.root(), "11222333");
+        assert_eq!(cnpj.to_string(), "11.222.333/0001-81");
+
+        assert_eq!("11.222.333/0001-82".parse::<Cnpj>(), Err(DocumentError::InvalidCheckDigit));
+        assert_eq!("00000000000000".parse::<Cnpj>(), Err(DocumentError::RepeatedDigits));
+    }
+
+    #[test]
+    fn test_alphanumeric_cnpj() {
+        let cnpj: Cnpj = "12.abc.345/01de-35".parse().unwrap();
+        assert_eq!(cnpj.as_str(), "12ABC34501DE35");
+        assert_eq!("12ABC34501DE36".parse::<Cnpj>(), Err(DocumentError::InvalidCheckDigit));
+        assert_eq!("12ABC34501DE3X".parse::<Cnpj>(), Err(DocumentError::InvalidCharacter('X')));
+    }
+}
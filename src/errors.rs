@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CepDetailedError {
     name: String,
     message: String,
@@ -18,24 +19,47 @@ pub struct CepDetailedError {
 //     }
 // }
 
+/// A failure coming from one of the configured base URLs
+#[derive(Debug, Clone)]
+pub struct BaseUrlError {
+    /// The base URL that produced the error
+    pub base_url: String,
+    /// What went wrong
+    pub error: Error,
+}
+
+/// A failure of one of the CEP providers of a `CepProviderChain`
+#[derive(Debug, Clone)]
+pub struct ProviderFailure {
+    /// The provider name (i.e: "viacep")
+    pub provider: String,
+    /// What went wrong
+    pub error: Error,
+}
+
 /// An enum representing the errors that can occur.
-#[derive(Debug)]
+/// It's cheap to clone, so a single failure can be shared between coalesced requests
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum Error {
     BrasilApiError {
         message: String,
         name: String,
         r#type: String,
+        /// The HTTP status of the answer (0 when unknown)
+        status: u16,
     },
     BrasilCepApiError {
         message: String,
         name: String,
         r#type: String,
-        errors: Vec<CepDetailedError>
+        errors: Vec<CepDetailedError>,
+        /// The HTTP status of the answer (0 when unknown)
+        status: u16,
     },
     NotExpectedRequestError,
-    HttpError(isahc::Error),
-    SerdeJsonError(serde_json::Error),
+    HttpError(Arc<isahc::Error>),
+    SerdeJsonError(Arc<serde_json::Error>),
     InvalidInputLenError {
         name: String,
         min: i32,
@@ -45,6 +69,44 @@ pub enum Error {
         name: String,
         min: i32,
         max: i32
+    },
+    /// The server answered with HTTP 429, `retry_after` comes from the `Retry-After` header
+    RateLimited {
+        retry_after: Option<std::time::Duration>
+    },
+    /// The circuit breaker of this endpoint family is open, no request was sent
+    CircuitOpen {
+        family: crate::client::EndpointFamily,
+        retry_in: std::time::Duration
+    },
+    /// Every configured base URL failed, in the order they were tried
+    FailoverExhausted {
+        attempts: Vec<BaseUrlError>
+    },
+    /// A data file (i.e: a holidays table) could not be loaded
+    InvalidDataError {
+        message: String
+    },
+    /// Nothing matches the lookup of a field resolved locally (i.e: an ISPB on the banks directory)
+    NotFoundError {
+        name: String,
+        value: String
+    },
+    /// The answer contradicts itself (i.e: a CEP from the SP range answered as RJ)
+    InconsistentDataError {
+        name: String,
+        expected: String,
+        found: String
+    },
+    /// A service other than BrasilApi (i.e: ViaCEP) answered with an unexpected HTTP status
+    ProviderError {
+        provider: String,
+        status: u16,
+        message: String
+    },
+    /// Every CEP provider of the chain failed, in the order they were configured
+    ProvidersExhausted {
+        attempts: Vec<ProviderFailure>
     }
 }
 
@@ -66,13 +128,16 @@ impl std::fmt::Display for Error {
             Error::BrasilApiError {
                 message,
                 name,
-                r#type
+                r#type,
+                status
             } => write!(
                 fmt,
                 "Error during BrasilApiCall [
+                    status: {}
                     message: {}
                     name: {}
                     type: {}]",
+                status,
                 message,
                 name,
                 r#type,
@@ -81,14 +146,17 @@ impl std::fmt::Display for Error {
                 message,
                 name,
                 r#type,
-                errors
+                errors,
+                status
             } => write!(
                 fmt,
                 "Error during BrasilApiCall [CEP] [
+                    status: {}
                     message: {}
                     name: {}
                     type: {}
                     errors: {:#?}]",
+                status,
                 message,
                 name,
                 r#type,
@@ -96,7 +164,7 @@ impl std::fmt::Display for Error {
             ),
             Error::HttpError(e) => write!(fmt, "HTTP request failed: {}", e),
             Error::NotExpectedRequestError => write!(fmt, "Not Expected Error"),
-            Error::SerdeJsonError(_) => todo!(),
+            Error::SerdeJsonError(e) => write!(fmt, "Invalid JSON: {}", e),
             Error::InvalidInputLenError { name, min, max } => write!(fmt,
             "Field [{}] expected length should be between {} and {}",
             name, 
@@ -107,12 +175,81 @@ impl std::fmt::Display for Error {
                 name, 
                 min, 
                 max),
+            Error::RateLimited { retry_after: Some(retry_after) } => write!(fmt,
+                "Rate limited by BrasilApi, retry after {}s",
+                retry_after.as_secs()),
+            Error::RateLimited { retry_after: None } => write!(fmt, "Rate limited by BrasilApi"),
+            Error::CircuitOpen { family, retry_in } => write!(fmt,
+                "Circuit open for {:?}, retry in {}ms",
+                family,
+                retry_in.as_millis()),
+            Error::InvalidDataError { message } => write!(fmt, "Invalid data: {}", message),
+            Error::NotFoundError { name, value } => write!(fmt, "Nothing found for [{}] = {}", name, value),
+            Error::InconsistentDataError { name, expected, found } => write!(fmt,
+                "Field [{}] expected {}, found {}",
+                name,
+                expected,
+                found),
+            Error::ProviderError { provider, status, message } => write!(fmt,
+                "Provider [{}] answered with HTTP {}: {}",
+                provider,
+                status,
+                message),
+            Error::ProvidersExhausted { attempts } => {
+                write!(fmt, "Every CEP provider failed [")?;
+                for attempt in attempts {
+                    write!(fmt, "\n    {}: {}", attempt.provider, attempt.error)?;
+                }
+                write!(fmt, "]")
+            },
+            Error::FailoverExhausted { attempts } => {
+                write!(fmt, "Every base URL failed [")?;
+                for attempt in attempts {
+                    write!(fmt, "\n    {}: {}", attempt.base_url, attempt.error)?;
+                }
+                write!(fmt, "]")
+            },
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// Whether BrasilApi could not answer (unreachable, 5xx or rate limited), as opposed to answering with an error
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self,
+            Error::NotExpectedRequestError
+            | Error::HttpError(_)
+            | Error::RateLimited { .. }
+            | Error::CircuitOpen { .. }
+            | Error::FailoverExhausted { .. })
+        || self.status().is_some_and(|status| status >= 500)
+    }
+
+    /// The HTTP status BrasilApi answered with, for its own errors
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::BrasilApiError { status, .. } | Error::BrasilCepApiError { status, .. } if *status != 0 => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Whether BrasilApi answered 404 (i.e: an unknown DDD or CEP)
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    /// The error BrasilApi answered with, keeping the HTTP status
+    pub(crate) fn from_response(status: u16, json: &serde_json::Value) -> Error {
+        match Error::from(json) {
+            Error::BrasilApiError { message, name, r#type, .. } => Error::BrasilApiError { message, name, r#type, status },
+            Error::BrasilCepApiError { message, name, r#type, errors, .. } => Error::BrasilCepApiError { message, name, r#type, errors, status },
+            other => other,
+        }
+    }
+}
+
 impl From<&serde_json::Value> for Error {
     fn from(json: &serde_json::Value) -> Error {
 
@@ -126,19 +263,20 @@ impl From<&serde_json::Value> for Error {
             .get("name")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string())
-            .unwrap_or_else(String::new);
+            .unwrap_or_default();
 
         let ztype = json
         .get("type")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string())
-        .unwrap_or_else(String::new);
+        .unwrap_or_default();
 
         if !json["errors"].is_array() {
             return Error::BrasilApiError {
                 message,
                 name,
                 r#type: ztype,
+                status: 0,
             };
         }
 
@@ -148,17 +286,24 @@ impl From<&serde_json::Value> for Error {
             message,
             name,
             r#type: ztype,
-            errors
+            errors,
+            status: 0
         }
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::SerdeJsonError(Arc::new(error))
+    }
+}
+
 impl From<isahc::Error> for Error {
     fn from(error: isahc::Error) -> Error {
         if error.kind() == isahc::error::ErrorKind::ConnectionFailed {
             Error::NotExpectedRequestError
         } else {
-            Error::HttpError(error)
+            Error::HttpError(Arc::new(error))
         }
     }
 }
\ No newline at end of file
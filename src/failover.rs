@@ -0,0 +1,141 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How the client picks a base URL when more than one is configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailoverStrategy {
+    /// Always start with the first healthy base URL, the next ones are backups
+    PrimaryBackup,
+    /// Spread the calls between the healthy base URLs
+    RoundRobin,
+}
+
+/// The health of a configured base URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseUrlHealth {
+    /// The base URL
+    pub base_url: String,
+    /// `false` while the URL is cooling down after a transport error or 5xx answer
+    pub healthy: bool,
+    /// Failures in a row
+    pub consecutive_failures: u32,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    unhealthy_until: Option<Instant>,
+    consecutive_failures: u32,
+}
+
+impl Health {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.map_or(true, |until| until <= now)
+    }
+}
+
+/// The ordered base URLs of a client and their health, shared between clones
+#[derive(Debug, Clone)]
+pub(crate) struct BaseUrls {
+    urls: Arc<Vec<String>>,
+    strategy: FailoverStrategy,
+    pub(crate) cooldown: Duration,
+    health: Arc<Mutex<Vec<Health>>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl BaseUrls {
+    pub(crate) fn new(urls: Vec<String>, strategy: FailoverStrategy) -> BaseUrls {
+        let health = urls.iter().map(|_| Health::default()).collect();
+        BaseUrls {
+            urls: Arc::new(urls),
+            strategy,
+            cooldown: DEFAULT_COOLDOWN,
+            health: Arc::new(Mutex::new(health)),
+            next: Arc::default(),
+        }
+    }
+
+    /// The base URLs to try for a single call: healthy ones first, following the strategy
+    pub(crate) fn attempt_order(&self) -> Vec<(usize, &str)> {
+        let count = self.urls.len();
+        let start = match self.strategy {
+            FailoverStrategy::PrimaryBackup => 0,
+            FailoverStrategy::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % count.max(1),
+        };
+
+        let now = Instant::now();
+        let health = self.health.lock().unwrap();
+        let mut order: Vec<usize> = (0..count).map(|i| (start + i) % count).collect();
+        // Unhealthy URLs are still tried, as a last resort
+        order.sort_by_key(|i| !health[*i].is_healthy(now));
+
+        order.into_iter().map(|i| (i, self.urls[i].as_str())).collect()
+    }
+
+    pub(crate) fn mark(&self, index: usize, success: bool) {
+        let mut health = self.health.lock().unwrap();
+        let health = &mut health[index];
+        if success {
+            *health = Health::default();
+        } else {
+            health.consecutive_failures += 1;
+            health.unhealthy_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    pub(crate) fn health(&self) -> Vec<BaseUrlHealth> {
+        let now = Instant::now();
+        let health = self.health.lock().unwrap();
+        self.urls
+            .iter()
+            .zip(health.iter())
+            .map(|(base_url, health)| BaseUrlHealth {
+                base_url: base_url.clone(),
+                healthy: health.is_healthy(now),
+                consecutive_failures: health.consecutive_failures,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls() -> Vec<String> {
+        vec!["http://a".to_string(), "http://b".to_string(), "http://c".to_string()]
+    }
+
+    fn order(base_urls: &BaseUrls) -> Vec<&str> {
+        base_urls.attempt_order().into_iter().map(|(_, url)| url).collect()
+    }
+
+    #[test]
+    fn test_primary_backup_skips_unhealthy() {
+        let base_urls = BaseUrls::new(urls(), FailoverStrategy::PrimaryBackup);
+        assert_eq!(order(&base_urls), vec!["http://a", "http://b", "http://c"]);
+
+        base_urls.mark(0, false);
+        assert_eq!(order(&base_urls), vec!["http://b", "http://c", "http://a"]);
+        assert!(!base_urls.health()[0].healthy);
+
+        base_urls.mark(0, true);
+        assert_eq!(order(&base_urls), vec!["http://a", "http://b", "http://c"]);
+    }
+
+    #[test]
+    fn test_round_robin_rotates() {
+        let base_urls = BaseUrls::new(urls(), FailoverStrategy::RoundRobin);
+        assert_eq!(order(&base_urls)[0], "http://a");
+        assert_eq!(order(&base_urls)[0], "http://b");
+        assert_eq!(order(&base_urls)[0], "http://c");
+        assert_eq!(order(&base_urls)[0], "http://a");
+    }
+}
@@ -17,10 +17,18 @@ extern crate lazy_static;
 This module should be used to get the Helper Client
 */
 pub mod client;
+pub mod api;
+pub mod boleto;
+pub mod calendar;
+pub mod circuit_breaker;
 pub mod definitions;
+pub mod documents;
 pub mod constants;
 pub mod errors;
+pub mod failover;
+pub mod rate_limit;
 mod request;
+mod text;
 
 pub use crate::definitions::*;
 pub mod commons;
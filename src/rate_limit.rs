@@ -0,0 +1,190 @@
+use crate::client::EndpointFamily;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/**
+Token bucket settings used to throttle requests before they leave the client <br />
+Example: `RateLimit::new(10, Duration::from_secs(1))` - 10 requests per second
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// How many requests are allowed on each period
+    pub requests: u32,
+    /// The period which `requests` refers to
+    pub per: Duration,
+    /// How many requests can be sent at once, after the client stayed idle
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub fn new(requests: u32, per: Duration) -> RateLimit {
+        RateLimit {
+            requests,
+            per,
+            burst: requests,
+        }
+    }
+
+    /// Shortcut for `requests` per second
+    pub fn per_second(requests: u32) -> RateLimit {
+        RateLimit::new(requests, Duration::from_secs(1))
+    }
+
+    /// Shortcut for `requests` per minute
+    pub fn per_minute(requests: u32) -> RateLimit {
+        RateLimit::new(requests, Duration::from_secs(60))
+    }
+
+    /// Changes the bucket capacity (the amount of requests allowed at once)
+    pub fn with_burst(self, burst: u32) -> RateLimit {
+        RateLimit { burst, ..self }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(limit: RateLimit, now: Instant) -> TokenBucket {
+        let capacity = limit.burst.max(1) as f64;
+        TokenBucket {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: limit.requests.max(1) as f64 / limit.per.as_secs_f64().max(f64::EPSILON),
+            last_refill: now,
+            paused_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Takes one token, or tells how long the caller should wait before trying again
+    pub(crate) fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Err(until - now);
+            }
+            // Resumes with a single token, so the first request probes the server again
+            self.paused_until = None;
+            self.tokens = 1.0;
+            self.last_refill = until;
+        }
+
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec))
+    }
+
+    /// Holds every request until `until`, used when the server answers with a 429
+    pub(crate) fn pause_until(&mut self, until: Instant) {
+        if self.paused_until.map_or(true, |current| current < until) {
+            self.paused_until = Some(until);
+        }
+        self.tokens = 0.0;
+    }
+}
+
+/// Client side rate limiter, shared between every clone of the client
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RateLimiter {
+    global: Option<Arc<Mutex<TokenBucket>>>,
+    families: HashMap<EndpointFamily, Arc<Mutex<TokenBucket>>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn set_global(&mut self, limit: RateLimit) {
+        self.global = Some(Arc::new(Mutex::new(TokenBucket::new(limit, Instant::now()))));
+    }
+
+    pub(crate) fn set_family(&mut self, family: EndpointFamily, limit: RateLimit) {
+        self.families.insert(
+            family,
+            Arc::new(Mutex::new(TokenBucket::new(limit, Instant::now()))),
+        );
+    }
+
+    fn buckets(&self, family: EndpointFamily) -> impl Iterator<Item = &Arc<Mutex<TokenBucket>>> {
+        self.families.get(&family).into_iter().chain(self.global.iter())
+    }
+
+    /// Waits (asynchronously) until both the family and the global buckets allow a new request
+    pub(crate) async fn acquire(&self, family: EndpointFamily) {
+        for bucket in self.buckets(family) {
+            loop {
+                let wait = bucket.lock().unwrap().try_acquire(Instant::now());
+                match wait {
+                    Ok(()) => break,
+                    Err(wait) => futures_timer::Delay::new(wait).await,
+                }
+            }
+        }
+    }
+
+    /// Honors the server `Retry-After`, holding the next requests on the same buckets
+    pub(crate) fn backoff(&self, family: EndpointFamily, retry_after: Duration) {
+        let until = Instant::now() + retry_after;
+        for bucket in self.buckets(family) {
+            bucket.lock().unwrap().pause_until(until);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_waits() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(RateLimit::per_second(2), start);
+
+        assert!(bucket.try_acquire(start).is_ok());
+        assert!(bucket.try_acquire(start).is_ok());
+
+        let wait = bucket.try_acquire(start).unwrap_err();
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+
+        assert!(bucket.try_acquire(start + Duration::from_millis(500)).is_ok());
+    }
+
+    #[test]
+    fn test_bucket_never_exceeds_capacity() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(RateLimit::per_second(1).with_burst(3), start);
+
+        let later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(bucket.try_acquire(later).is_ok());
+        }
+        assert!(bucket.try_acquire(later).is_err());
+    }
+
+    #[test]
+    fn test_bucket_pause() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(RateLimit::per_second(100), start);
+
+        bucket.pause_until(start + Duration::from_secs(2));
+        let wait = bucket.try_acquire(start + Duration::from_secs(1)).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        assert!(bucket.try_acquire(start + Duration::from_secs(2)).is_ok());
+        assert!(bucket.try_acquire(start + Duration::from_secs(2)).is_err());
+    }
+}
@@ -1,32 +1,189 @@
 
-use crate::errors::Error;
+use crate::{client::{BrasilApiClient, EndpointFamily}, errors::{BaseUrlError, Error}};
+use futures::future::{BoxFuture, FutureExt, Shared, WeakShared};
 use log::{Level, debug, error, log_enabled, trace, warn};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::de::DeserializeOwned;
 use serde_json::{from_str};
+use std::{collections::HashMap, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Duration};
 
-pub(crate) async fn get<Input: Serialize + std::fmt::Debug, Output: 'static + DeserializeOwned>(
+const TOO_MANY_REQUESTS: u16 = 429;
+
+/// The status and body of an answered request, before parsing
+#[derive(Debug, Clone)]
+pub(crate) struct RawResponse {
+    pub(crate) status: u16,
+    pub(crate) body: String,
+}
+
+type InFlightRequest = Shared<BoxFuture<'static, Result<RawResponse, Error>>>;
+/// Each request with an id, held weakly so one every caller gave up on is dropped instead of being joined
+type InFlightEntries = Arc<Mutex<HashMap<String, (u64, WeakShared<BoxFuture<'static, Result<RawResponse, Error>>>)>>>;
+
+/// Single-flight registry: concurrent calls for the same URL share one upstream request
+#[derive(Debug, Clone)]
+pub(crate) struct Coalescer {
+    pub(crate) enabled: bool,
+    in_flight: InFlightEntries,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Default for Coalescer {
+    fn default() -> Self {
+        Coalescer { enabled: true, in_flight: Arc::default(), next_id: Arc::default() }
+    }
+}
+
+/// Removes its in-flight entry once the request is answered, or dropped because every caller went away
+struct InFlightGuard {
+    in_flight: InFlightEntries,
+    key: String,
+    id: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            // A newer request for the same URL may have taken the place already
+            if in_flight.get(&self.key).map(|(id, _)| *id) == Some(self.id) {
+                in_flight.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl BrasilApiClient {
+    /// Every BrasilApi operation should go through here, so client settings are honored
+    pub(crate) async fn call<Output: 'static + DeserializeOwned>(
+        &self,
+        family: EndpointFamily,
+        path: &str
+    ) -> Result<Output, Error> {
+        let raw = if self.coalescer.enabled {
+            self.coalesced(family, path.to_string()).await?
+        } else {
+            self.send(family, path).await?
+        };
+
+        parse_response(raw.status, raw.body)
+    }
+
+    fn coalesced(&self, family: EndpointFamily, path: String) -> InFlightRequest {
+        let mut in_flight = self.coalescer.in_flight.lock().unwrap();
+        if let Some(request) = in_flight.get(&path).and_then(|(_, request)| request.upgrade()) {
+            trace!("Joining in-flight request on {}", path);
+            return request;
+        }
+
+        let id = self.coalescer.next_id.fetch_add(1, Ordering::Relaxed);
+        let guard = InFlightGuard { in_flight: self.coalescer.in_flight.clone(), key: path.clone(), id };
+        let cli = self.clone();
+        let request = async move {
+            let guard = guard;
+            cli.send(family, &guard.key).await
+        }.boxed().shared();
+
+        // Only the callers keep the request alive, the registry just lets new ones join it
+        if let Some(weak) = request.downgrade() {
+            in_flight.insert(path, (id, weak));
+        }
+        request
+    }
+
+    async fn send(&self, family: EndpointFamily, path: &str) -> Result<RawResponse, Error> {
+        let permit = self.circuit_breakers.acquire(family)
+            .map_err(|retry_in| Error::CircuitOpen { family, retry_in })?;
+
+        self.rate_limiter.acquire(family).await;
+
+        let resp = self.failover(path).await;
+        permit.record(!is_failure(&resp));
+
+        if let Err(Error::RateLimited { retry_after: Some(retry_after) }) = &resp {
+            self.rate_limiter.backoff(family, *retry_after);
+        }
+
+        resp
+    }
+}
+
+impl BrasilApiClient {
+    /**
+    Sends the request to each base URL, in order, until one of them answers properly <br />
+    Transport errors and 5xx answers come back as `Error::FailoverExhausted`, so they always tell the base URL, even with a single one
+    */
+    async fn failover(&self, path: &str) -> Result<RawResponse, Error> {
+        let mut attempts = vec![];
+        for (index, base_url) in self.base_urls.attempt_order() {
+            let resp = get(&format!("{}/{}", base_url, path)).await;
+            let failed = is_failure(&resp);
+            self.base_urls.mark(index, !failed);
+            if !failed {
+                return resp;
+            }
+
+            warn!("Request to {} failed, trying the next base URL", base_url);
+            let error = match resp {
+                Ok(raw) => parse_response::<serde_json::Value>(raw.status, raw.body).unwrap_err(),
+                Err(e) => e,
+            };
+            attempts.push(BaseUrlError { base_url: base_url.to_string(), error });
+        }
+
+        Err(Error::FailoverExhausted { attempts })
+    }
+}
+
+/// Whether the answer means the upstream is unhealthy
+fn is_failure(resp: &Result<RawResponse, Error>) -> bool {
+    match resp {
+        Ok(raw) => raw.status >= 500,
+        Err(Error::HttpError(_)) | Err(Error::NotExpectedRequestError) | Err(Error::FailoverExhausted { .. }) => true,
+        Err(_) => false,
+    }
+}
+
+pub(crate) async fn get(
     url: &str
-) -> Result<Output, Error> {
+) -> Result<RawResponse, Error> {
     use isahc::*;
 
     trace!("GET Req on {}", url);
 
     let mut resp = isahc::get_async(url)
-    .await
-    .map_err(|_|crate::errors::Error::NotExpectedRequestError)
-    .unwrap();
+    .await?;
 
     let status = resp.status().as_u16();
-    let mut body = resp.text().await.map_err(|e| crate::errors::Error::HttpError(e.into()))?;
+    if status == TOO_MANY_REQUESTS {
+        let retry_after = resp.headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        warn!("Rate limited by the server, retry after {:?}", retry_after);
+        return Err(crate::errors::Error::RateLimited { retry_after });
+    }
+
+    let mut body = resp.text().await.map_err(|e| crate::errors::Error::from(isahc::Error::from(e)))?;
 
     if body.is_empty() {
         body = "null".to_string();
     }
 
-    parse_response(status, body)
+    Ok(RawResponse { status, body })
 
 }
 
+/// `Retry-After` may be either the amount of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(secs as u64))
+}
+
 fn parse_response<Output: DeserializeOwned>(
     status_code: u16,
     body: String,
@@ -43,7 +200,7 @@ fn parse_response<Output: DeserializeOwned>(
             }
             Err(e) => {
                 error!("Request succeed but failed to parse response");
-                return Err(Error::SerdeJsonError(e));
+                return Err(Error::from(e));
             }
         };
     }
@@ -55,8 +212,261 @@ fn parse_response<Output: DeserializeOwned>(
                 debug!("Response: {}", &body);
             }
 
-            Err(Error::from(&e))
+            Err(Error::from_response(status_code, &e))
         },
-        Err(e) => Err(Error::SerdeJsonError(e)),
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        circuit_breaker::{CircuitBreakerConfig, CircuitState},
+        client::tests::{mock_server, MockResponse},
+        failover::FailoverStrategy,
+        rate_limit::RateLimit,
+    };
+    use futures_await_test::async_test;
+    use std::time::Instant;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::from_secs(0)));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[async_test]
+    async fn test_too_many_requests_is_typed() {
+        let server = mock_server(|_| MockResponse::json(429, "").header("Retry-After", "7"));
+        let resp = server.client().get_banks().await;
+
+        match resp {
+            Err(Error::RateLimited { retry_after }) => assert_eq!(retry_after, Some(Duration::from_secs(7))),
+            other => panic!("Expected RateLimited, got {:?}", other),
+        }
     }
-}
\ No newline at end of file
+
+    #[async_test]
+    async fn test_rate_limit_waits_instead_of_failing() {
+        let server = mock_server(|_| MockResponse::json(200, "[]"));
+        let cli = server.client()
+            .with_endpoint_rate_limit(EndpointFamily::Banks, RateLimit::new(1, Duration::from_millis(200)));
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(cli.get_banks().await.is_ok());
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        assert_eq!(server.hits(), 3);
+    }
+
+    #[async_test]
+    async fn test_concurrent_identical_calls_are_coalesced() {
+        let server = mock_server(|_| {
+            std::thread::sleep(Duration::from_millis(200));
+            MockResponse::json(200, r#"{"state":"AM","cities":["TEFÉ"]}"#)
+        });
+        let cli = server.client();
+
+        let calls = (0..5).map(|_| cli.get_city_and_states_by_ddd(&97));
+        let results = futures::future::join_all(calls).await;
+
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(server.hits(), 1);
+
+        assert!(cli.get_city_and_states_by_ddd(&97).await.is_ok());
+        assert_eq!(server.hits(), 2);
+    }
+
+    #[async_test]
+    async fn test_coalesced_errors_are_shared() {
+        let server = mock_server(|_| {
+            std::thread::sleep(Duration::from_millis(200));
+            MockResponse::json(404, r#"{"message":"DDD não encontrado","type":"ddd_error","name":"DDD_NOT_FOUND"}"#)
+        });
+        let cli = server.client();
+
+        let calls = (0..3).map(|_| cli.get_city_and_states_by_ddd(&26));
+        let results = futures::future::join_all(calls).await;
+
+        assert!(results.iter().all(|r| matches!(r, Err(Error::BrasilApiError { name, .. }) if name == "DDD_NOT_FOUND")));
+        assert_eq!(server.hits(), 1);
+    }
+
+    #[async_test]
+    async fn test_abandoned_coalesced_requests_are_forgotten() {
+        let server = mock_server(|_| {
+            std::thread::sleep(Duration::from_millis(100));
+            MockResponse::json(200, "[]")
+        });
+        let cli = server.client();
+
+        let mut abandoned = Box::pin(cli.get_banks());
+        assert!(futures::poll!(abandoned.as_mut()).is_pending());
+        assert_eq!(cli.coalescer.in_flight.lock().unwrap().len(), 1);
+
+        drop(abandoned);
+        assert!(cli.coalescer.in_flight.lock().unwrap().is_empty());
+
+        // A new call sends its own request instead of joining the dropped one
+        assert!(cli.get_banks().await.unwrap().is_empty());
+        assert!(cli.coalescer.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[async_test]
+    async fn test_coalescing_can_be_disabled() {
+        let server = mock_server(|_| {
+            std::thread::sleep(Duration::from_millis(100));
+            MockResponse::json(200, "[]")
+        });
+        let cli = server.client().with_request_coalescing(false);
+
+        let results = futures::future::join_all((0..3).map(|_| cli.get_banks())).await;
+
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(server.hits(), 3);
+    }
+
+    #[async_test]
+    async fn test_circuit_opens_and_recovers() {
+        let healthy = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let server_healthy = healthy.clone();
+        let server = mock_server(move |_| {
+            if server_healthy.load(std::sync::atomic::Ordering::SeqCst) {
+                MockResponse::json(200, "[]")
+            } else {
+                MockResponse::json(503, r#"{"message":"Todos os serviços retornaram erro","type":"service_error","name":"BANKS_ERROR"}"#)
+            }
+        });
+        let cli = server.client().with_endpoint_circuit_breaker(EndpointFamily::Banks, CircuitBreakerConfig {
+            failure_rate_threshold: 0.5,
+            minimum_calls: 2,
+            window_size: 2,
+            open_interval: Duration::from_millis(200),
+            half_open_probes: 1,
+        });
+
+        assert_eq!(cli.circuit_state(EndpointFamily::Banks), Some(CircuitState::Closed));
+        assert_eq!(cli.circuit_state(EndpointFamily::Cep), None);
+        assert!(cli.get_banks().await.is_err());
+        assert!(cli.get_banks().await.is_err());
+
+        assert!(matches!(cli.circuit_state(EndpointFamily::Banks), Some(CircuitState::Open { .. })));
+        match cli.get_banks().await {
+            Err(Error::CircuitOpen { family, .. }) => assert_eq!(family, EndpointFamily::Banks),
+            other => panic!("Expected CircuitOpen, got {:?}", other),
+        }
+        assert_eq!(server.hits(), 2);
+
+        futures_timer::Delay::new(Duration::from_millis(250)).await;
+        healthy.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(cli.circuit_state(EndpointFamily::Banks), Some(CircuitState::HalfOpen));
+        assert!(cli.get_banks().await.is_ok());
+        assert_eq!(cli.circuit_state(EndpointFamily::Banks), Some(CircuitState::Closed));
+    }
+
+    fn ok_server() -> crate::client::tests::MockServer {
+        mock_server(|_| MockResponse::json(200, "[]"))
+    }
+
+    fn failing_server() -> crate::client::tests::MockServer {
+        mock_server(|_| MockResponse::json(500, r#"{"message":"Erro interno","type":"internal","name":"INTERNAL"}"#))
+    }
+
+    fn closed_port_url() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[async_test]
+    async fn test_failover_to_backup() {
+        let primary = failing_server();
+        let backup = ok_server();
+        let cli = BrasilApiClient::new_with_base_urls(
+            vec![primary.base_url.clone(), backup.base_url.clone()],
+            FailoverStrategy::PrimaryBackup
+        );
+
+        assert!(cli.get_banks().await.is_ok());
+        assert_eq!((primary.hits(), backup.hits()), (1, 1));
+        assert!(!cli.base_url_health()[0].healthy);
+
+        // The primary is cooling down, so it's skipped
+        assert!(cli.get_banks().await.is_ok());
+        assert_eq!((primary.hits(), backup.hits()), (1, 2));
+    }
+
+    #[async_test]
+    async fn test_failover_on_transport_error() {
+        let backup = ok_server();
+        let cli = BrasilApiClient::new_with_base_urls(
+            vec![closed_port_url(), backup.base_url.clone()],
+            FailoverStrategy::PrimaryBackup
+        );
+
+        assert!(cli.get_banks().await.is_ok());
+        assert_eq!(backup.hits(), 1);
+    }
+
+    #[async_test]
+    async fn test_failover_exhausted_reports_base_urls() {
+        let first = failing_server();
+        let dead = closed_port_url();
+        let cli = BrasilApiClient::new_with_base_urls(
+            vec![first.base_url.clone(), dead.clone()],
+            FailoverStrategy::PrimaryBackup
+        );
+
+        match cli.get_banks().await {
+            Err(Error::FailoverExhausted { attempts }) => {
+                let base_urls: Vec<&str> = attempts.iter().map(|a| a.base_url.as_str()).collect();
+                assert_eq!(base_urls, vec![first.base_url.as_str(), dead.as_str()]);
+                assert!(matches!(attempts[0].error, Error::BrasilApiError { .. }));
+            },
+            other => panic!("Expected FailoverExhausted, got {:?}", other),
+        }
+    }
+
+    #[async_test]
+    async fn test_single_base_url_failures_report_it() {
+        let server = mock_server(|_| MockResponse::json(502, "<html><body><h1>502 Bad Gateway</h1></body></html>"));
+
+        let error = server.client().get_banks().await.unwrap_err();
+        match &error {
+            Error::FailoverExhausted { attempts } => {
+                assert_eq!(attempts.len(), 1);
+                assert_eq!(attempts[0].base_url, server.base_url);
+                assert!(matches!(attempts[0].error, Error::SerdeJsonError(_)));
+            },
+            other => panic!("Expected FailoverExhausted, got {:?}", other),
+        }
+        assert!(error.to_string().contains(&format!("{}: Invalid JSON: ", server.base_url)));
+        assert!(error.is_unavailable());
+    }
+
+    #[test]
+    fn test_empty_base_urls_use_the_default() {
+        let cli = BrasilApiClient::new_with_base_urls(Vec::<String>::new(), FailoverStrategy::PrimaryBackup);
+        let health = cli.base_url_health();
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].base_url, crate::constants::DEFAULT_BASE_URL);
+    }
+
+    #[async_test]
+    async fn test_round_robin() {
+        let first = ok_server();
+        let second = ok_server();
+        let cli = BrasilApiClient::new_with_base_urls(
+            vec![first.base_url.clone(), second.base_url.clone()],
+            FailoverStrategy::RoundRobin
+        );
+
+        for _ in 0..4 {
+            assert!(cli.get_banks().await.is_ok());
+        }
+        assert_eq!((first.hits(), second.hits()), (2, 2));
+    }
+}
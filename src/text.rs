@@ -0,0 +1,106 @@
+//! Text helpers shared by the offline features
+
+/// Replaces the accented latin letters by their plain ASCII version (i.e: "São Paulo" -> "Sao Paulo")
+pub(crate) fn strip_accents(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+            'Á' | 'À' | 'Â' | 'Ã' | 'Ä' | 'Å' => 'A',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'É' | 'È' | 'Ê' | 'Ë' => 'E',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'Í' | 'Ì' | 'Î' | 'Ï' => 'I',
+            'ó' | 'ò' | 'ô' | 'õ' | 'ö' => 'o',
+            'Ó' | 'Ò' | 'Ô' | 'Õ' | 'Ö' => 'O',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'Ú' | 'Ù' | 'Û' | 'Ü' => 'U',
+            'ç' => 'c',
+            'Ç' => 'C',
+            'ñ' => 'n',
+            'Ñ' => 'N',
+            other => other,
+        })
+        .collect()
+}
+
+/// Lowercase ASCII slug, with every other char turned into a single `-`
+pub(crate) fn slug(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in strip_accents(text).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/**
+Comparison form of a text: no accents, lowercase, words separated by a single space <br />
+Example: "SÃO PAULO" and " São  Paulo" both become "sao paulo"
+*/
+pub(crate) fn normalize(text: &str) -> String {
+    strip_accents(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Edit distance between two texts, in chars
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Levenshtein based similarity, from 0.0 (nothing alike) to 1.0 (same text)
+pub(crate) fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_accents() {
+        assert_eq!(strip_accents("SÃO PAULO DE OLIVENÇA"), "SAO PAULO DE OLIVENCA");
+        assert_eq!(strip_accents("Confraternização"), "Confraternizacao");
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Dia da consciência negra"), "dia-da-consciencia-negra");
+        assert_eq!(slug(" Proclamação da República! "), "proclamacao-da-republica");
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("SÃO PAULO"), "sao paulo");
+        assert_eq!(normalize(" São  Paulo "), "sao paulo");
+        assert_eq!(normalize("BCO DO BRASIL S.A."), "bco do brasil s a");
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("santander", "santnder"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("itau", "itau"), 0);
+        assert!((similarity("santander", "santnder") - 8.0 / 9.0).abs() < f64::EPSILON);
+    }
+}